@@ -51,7 +51,25 @@ fn main() -> CLIResult {
                 file.write_all(&code).unwrap();
                 Ok(())
             } else if &arg == "run" {
-                let file = args.next().ok_or(CLIError::InsufficientArguments)?;
+                // blush run [--seed N] [--allow-env] <file> [-- args...]
+                let mut seed = None;
+                let mut allow_env = false;
+                let file = loop {
+                    let arg = args.next().ok_or(CLIError::InsufficientArguments)?;
+                    match arg.as_str() {
+                        "--seed" => {
+                            let n = args.next().ok_or(CLIError::InsufficientArguments)?;
+                            seed = Some(n.parse::<u64>().map_err(|_| CLIError::UnkownArgument(n))?);
+                        }
+                        "--allow-env" => allow_env = true,
+                        _ => break arg,
+                    }
+                };
+                let script_args: Vec<String> = match args.next() {
+                    Some(arg) if arg == "--" => args.collect(),
+                    Some(arg) => return Err(CLIError::UnkownArgument(arg)),
+                    None => vec![],
+                };
                 let bytecode = match read(&file) {
                     Ok(b) => b,
                     Err(e) => match e.kind() {
@@ -73,8 +91,18 @@ fn main() -> CLIResult {
                 };
 
                 let mut runtime = blush::Runtime::new(bags, None, scope);
+                if let Some(seed) = seed {
+                    runtime.seed(seed);
+                }
+                if allow_env {
+                    runtime.env.allow_all();
+                }
+                runtime.args = script_args;
                 runtime.exec().unwrap();
-                Ok(())
+                match runtime.exit_code {
+                    Some(code) => std::process::exit(code),
+                    None => Ok(()),
+                }
             } else {
                 Err(CLIError::UnkownArgument(arg))
             }
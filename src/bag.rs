@@ -1,4 +1,5 @@
 use crate::{Instruction, Value, error::bag::*};
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct Bag {
@@ -16,7 +17,7 @@ pub struct ZippedBag {
 	pub bytes_len: u8,
 }
 
-#[derive(Debug, Default, Clone, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
 pub struct OpenedBag {
 	pub constants: Vec<u8>,
 	pub bytecode: Vec<u8>,
@@ -63,8 +64,8 @@ impl Bag {
 	}
 
 	pub fn check_length(&mut self, increase: (usize, usize)) -> BagResult {
-		let bytecode = (self.num_bytes as usize + increase.0) >= (u8::MAX as usize);
-		let constants = (self.num_constants as usize + increase.1) >= (u8::MAX as usize);
+		let bytecode = (self.num_bytes + increase.0) >= (u8::MAX as usize);
+		let constants = (self.num_constants + increase.1) >= (u8::MAX as usize);
 		let culprit = if bytecode {
 			BagItem::Bytecode
 		} else if constants {
@@ -87,9 +88,11 @@ impl Bag {
         let serialized = bincode::serialize(&value).unwrap();
         let len = serialized.len();
 
+		// Make sure both the instruction and the value fit before touching either
+		self.check_length((4, len))?;
         self.constants.extend(serialized);
 		self.num_constants += len;
-        self.emit_byte(Instruction::Const, &vec![store, len as u8, idx as u8])?;
+        self.emit_byte(Instruction::Const, &vec![idx as u8, len as u8, store])?;
         Ok(())
 	}
 
@@ -102,6 +105,10 @@ impl Bag {
 		Ok(())
 	}
 
+	pub fn is_empty(&self) -> bool {
+		self.num_bytes == 0 && self.num_constants == 0
+	}
+
 	// Clean up step implemented now in case extra data needs to be computed
 	pub fn zip_up(self) -> ZippedBag {
 		let mut _self = self.clone();
@@ -5,9 +5,12 @@
 	allows the higher precedence or enclosing expressions to easily reference the value for later use.
  */
 
-use crate::{Instruction, TokenKind, Value, Bag, ZippedBag, runtime::{CompilerScope, Local}, error::{bag::BagError, compiler::*}};
+use crate::{Instruction, TokenKind, Value, Bag, ZippedBag, scanner::{string_parts, StrPart}, runtime::{CompilerScope, Function, Local, Param, BUILTINS, stdlib::{self, Member}}, error::{bag::BagError, compiler::*}};
 use logos::{Lexer, Logos};
 
+mod typecheck;
+pub use typecheck::*;
+
 #[derive(Clone)]
 pub struct Compiler<'src> {
     pub lexer: Lexer<'src, TokenKind>,
@@ -18,6 +21,27 @@ pub struct Compiler<'src> {
     pub previous_slice: String,
     pub current: Option<TokenKind>,
     pub scope: CompilerScope,
+    /// Variables and constants that are currently in scope
+    pub locals: Vec<Binding>,
+    /// How many function declarations the compiler is inside of
+    pub functions: u8,
+    /// Names of the native functions that scripts can call, starting with the runtime's builtins
+    pub natives: Vec<String>,
+    /// The register holding the value of the final statement, if it is an expression at the top level
+    pub result: Option<u8>,
+}
+
+/// Something that a name in scope refers to
+#[derive(Clone, Debug, PartialEq)]
+pub enum Binding {
+    /// A variable, by its index in `scope.vars`
+    Local(u8),
+    /// A constant, which is inlined wherever it is used
+    Const {
+        name: String,
+        value: Value,
+        depth: u8,
+    },
 }
 
 impl Default for Compiler<'_> {
@@ -31,6 +55,10 @@ impl Default for Compiler<'_> {
             current: None,
             previous_slice: "".into(),
             scope: CompilerScope::default(),
+            locals: vec![],
+            functions: 0,
+            natives: BUILTINS.iter().map(|b| b.to_string()).collect(),
+            result: None,
         }
     }
 }
@@ -43,9 +71,19 @@ impl<'s> Compiler<'s> {
 		}
 	}
 
+    /// Let scripts call the native function `name`, which must be registered with the runtime under
+    /// the same name before the program is run
+    pub fn declare_native(&mut self, name: impl Into<String>) -> &mut Self {
+        self.natives.push(name.into());
+        self
+    }
+
     /// Take the array of tokens and generate bytecode
+    /// The program is type checked before any bytecode is generated
     pub fn compile(&mut self) -> CompilerResult {
-        while self.peek() != None {
+        TypeChecker::new(self.lexer.source()).check()?;
+        while self.peek().is_some() {
+            self.result = None;
             self.declaration()?;
         }
         self.consume(None, "Expected end of expression")?;
@@ -89,6 +127,11 @@ impl<'s> Compiler<'s> {
         self.lexer.clone().peekable().peek().cloned()
     }
 
+    /// Get the token after the next token without advancing
+    pub(crate) fn peek_second(&self) -> Option<TokenKind> {
+        self.lexer.clone().nth(1)
+    }
+
     /// Get the next available register to store a value in
     pub(crate) fn use_register(&mut self) -> CompilerResult<u8> {
         if self.registers.is_empty() {
@@ -100,7 +143,10 @@ impl<'s> Compiler<'s> {
 
     /// Free a register
     pub(crate) fn free_register(&mut self, register: u8) {
-        self.registers.push(register)
+        // A register may be freed more than once, and must not be handed out twice
+        if !self.registers.contains(&register) {
+            self.registers.push(register)
+        }
     }
 
 	pub fn new_bag(&mut self) {
@@ -136,10 +182,14 @@ impl<'s> Compiler<'s> {
 			Ok(()) => {},
 			Err(e) => {
 				match e {
+					BagError::Full(_) if self.current_bag.is_empty() => {
+						// The value doesn't even fit in a fresh bag
+						Err(CompilerError::ExternalError("BagError".into(), e.to_string()))?;
+					}
 					BagError::Full(_) => {
 						self.new_bag();
 						self.free_register(store);
-						self.emit_const(value)?;
+						return self.emit_const(value);
 					}
 					_ => {
 						Err(CompilerError::ExternalError("BagError".into(), e.to_string()))?;
@@ -152,6 +202,40 @@ impl<'s> Compiler<'s> {
 		Ok(store)
     }
 
+    /// Start a new bag unless the current one has room for `bytes` more bytes of bytecode.
+    /// Used to keep instructions that refer to each other's positions in the same bag.
+    pub(crate) fn reserve(&mut self, bytes: usize) {
+        if self.current_bag.check_length((bytes, 0)).is_err() {
+            self.new_bag();
+        }
+    }
+
+    /// The bag index and offset that the next emitted instruction will be written to
+    pub(crate) fn position(&self) -> (u8, u8) {
+        (self.baggage.len() as u8, self.current_bag.bytecode.len() as u8)
+    }
+
+    /// Emit a [Instruction::Jump] to `target`, returning the position of the jump so that it can
+    /// be patched later if the target isn't known yet
+    pub(crate) fn emit_jump(&mut self, target: (u8, u8)) -> CompilerResult<(u8, u8)> {
+        self.reserve(3);
+        let at = self.position();
+        self.emit_byte(Instruction::Jump, vec![target.0, target.1])?;
+        Ok(at)
+    }
+
+    /// Point the [Instruction::Jump] at `at` to `target`
+    pub(crate) fn patch_jump(&mut self, at: (u8, u8), target: (u8, u8)) {
+        let (bag, offset) = (at.0 as usize, at.1 as usize);
+        let bytecode: &mut [u8] = if bag == self.baggage.len() {
+            &mut self.current_bag.bytecode
+        } else {
+            &mut self.baggage[bag].bytecode
+        };
+        bytecode[offset + 1] = target.0;
+        bytecode[offset + 2] = target.1;
+    }
+
     /// Check if the next token is expected
     pub(crate) fn tag(&mut self, expected: Option<TokenKind>) -> bool {
         if self.peek() == expected {
@@ -175,6 +259,10 @@ impl<'s> Compiler<'s> {
     pub(crate) fn declaration(&mut self) -> CompilerResult {
         if self.tag(Some(TokenKind::Let)) {
             self.let_declaration()
+        } else if self.tag(Some(TokenKind::Const)) {
+            self.const_declaration()
+        } else if self.tag(Some(TokenKind::Fn)) {
+            self.fn_declaration()
         } else {
             self.statement()?;
             Ok(())
@@ -182,7 +270,9 @@ impl<'s> Compiler<'s> {
     }
 
     pub(crate) fn let_declaration(&mut self) -> CompilerResult {
-        let global = self.parse_variable("Expected variable name after 'let'.")?;
+        let mutable = self.tag(Some(TokenKind::Mut));
+        let global = self.parse_variable("Expected variable name after 'let'.", mutable)?;
+        self.skip_annotation();
 
         self.consume(Some(TokenKind::Equal), "Variables must be initialized.")?;
         let v = self.expression()?;
@@ -195,30 +285,236 @@ impl<'s> Compiler<'s> {
         Ok(())
     }
 
+    /// Parse a constant declaration
+    /// i.e. parse `const X = 1;`
+    /// Nothing is emitted for the declaration, instead the value is inlined wherever `X` is used
+    pub(crate) fn const_declaration(&mut self) -> CompilerResult {
+        self.consume(Some(TokenKind::Identifier), "Expected constant name after 'const'.")?;
+        let name = self.lexer.slice().to_string();
+        self.skip_annotation();
+        self.consume(Some(TokenKind::Equal), "Constants must be initialized.")?;
+
+        let not_constant = CompilerError::ScopeError(ScopeError::NotConstant(name.clone()));
+        let value = match self.next() {
+            Some(TokenKind::Number(n)) => Value::VNumber(n),
+            Some(TokenKind::Bool(b)) => Value::VBool(b),
+            Some(TokenKind::Nil) => Value::VNil,
+            Some(TokenKind::Str) => {
+                let start = self.lexer.span().start + 1;
+                match string_parts(&self.lexer.source()[start..], start) {
+                    Some((parts, _)) => match &parts[..] {
+                        [StrPart::Text(text)] => Value::VString(text.clone()),
+                        _ => return Err(not_constant),
+                    },
+                    None => return Err(not_constant),
+                }
+            }
+            Some(TokenKind::Identifier) => match self.resolve(self.lexer.slice())? {
+                Binding::Const { value, .. } => value,
+                Binding::Local(_) => return Err(not_constant),
+            },
+            _ => return Err(not_constant),
+        };
+        self.consume(
+            Some(TokenKind::Semicolon),
+            "Expected ';' after constant declaration",
+        )?;
+
+        self.locals.push(Binding::Const {
+            name,
+            value,
+            depth: self.scope.depth,
+        });
+        Ok(())
+    }
+
+    /// Parse a function declaration
+    /// i.e. parse `fn greet(name, greeting = "hi", ...rest) { ... }`
+    /// The function's code is emitted in place, behind a jump over it, and is followed by the
+    /// constant function value being stored in the function's variable.
+    pub(crate) fn fn_declaration(&mut self) -> CompilerResult {
+        // Declared before the body so that the function can call itself
+        let slot = self.parse_variable("Expected function name after 'fn'.", false)?;
+        let name = self.lexer.slice().to_string();
+        let over = self.emit_jump((0, 0))?;
+        let entry = self.position();
+
+        self.begin_scope();
+        self.functions += 1;
+        let first_slot = self.scope.vars.len() as u8;
+        self.consume(Some(TokenKind::LeftParen), "Expected '(' after function name.")?;
+        let mut params = vec![];
+        while !self.tag(Some(TokenKind::RightParen)) {
+            let param = self.param()?;
+            if params.iter().any(|p: &Param| p.name == param.name) {
+                return Err(CompilerError::ScopeError(ScopeError::DuplicateParameter {
+                    function: name,
+                    name: param.name,
+                }));
+            }
+            params.push(param);
+            if !self.tag(Some(TokenKind::Comma)) {
+                self.consume(Some(TokenKind::RightParen), "Expected ')' after parameters.")?;
+                break;
+            }
+        }
+        if self.tag(Some(TokenKind::Arrow)) {
+            // The return type has already been checked
+            self.next();
+        }
+
+        self.consume(Some(TokenKind::LeftBrace), "Expected '{' before function body.")?;
+        self.block()?;
+        // Functions that don't return anything return nil
+        let nil = self.emit_const(Value::VNil)?;
+        self.emit_byte(Instruction::Return, vec![nil])?;
+        self.free_register(nil);
+        self.functions -= 1;
+        self.end_scope();
+
+        let end = self.position();
+        self.patch_jump(over, end);
+        let function = self.emit_const(Value::VFunction(Box::new(Function {
+            name,
+            entry,
+            params,
+            slots: (first_slot, self.scope.vars.len() as u8),
+        })))?;
+        self.define_variable(slot, function)
+    }
+
+    /// Parse a function parameter, emitting the code for its default value if it has one
+    pub(crate) fn param(&mut self) -> CompilerResult<Param> {
+        let rest = self.tag(Some(TokenKind::Ellipsis));
+        let slot = self.parse_variable("Expected parameter name.", false)?;
+        let name = self.lexer.slice().to_string();
+        self.skip_annotation();
+
+        let default = !rest && self.tag(Some(TokenKind::Equal));
+        if default {
+            // MISSING and the jump over the default that it skips must share a bag
+            self.reserve(5);
+            self.emit_byte(Instruction::Missing, vec![slot])?;
+            let given = self.emit_jump((0, 0))?;
+            let value = self.expression()?;
+            self.define_variable(slot, value)?;
+            let end = self.position();
+            self.patch_jump(given, end);
+        }
+        Ok(Param {
+            name,
+            slot,
+            default,
+            rest,
+        })
+    }
+
+    /// Parse a return statement
+    /// i.e. parse `return x;` or `return;`
+    pub(crate) fn return_statement(&mut self) -> CompilerResult<u8> {
+        if self.functions == 0 {
+            return Err(CompilerError::ScopeError(ScopeError::ReturnOutsideFunction));
+        }
+        let value = if self.peek() == Some(TokenKind::Semicolon) {
+            self.emit_const(Value::VNil)?
+        } else {
+            self.expression()?
+        };
+        self.consume(Some(TokenKind::Semicolon), "Expected ';' after return value.")?;
+        self.emit_byte(Instruction::Return, vec![value])?;
+        self.free_register(value);
+        Ok(0)
+    }
+
+    /// Skip over a type annotation, which has already been checked
+    pub(crate) fn skip_annotation(&mut self) {
+        if self.tag(Some(TokenKind::Colon)) {
+            self.next();
+        }
+    }
+
     pub(crate) fn statement(&mut self) -> CompilerResult<u8> {
         if self.tag(Some(TokenKind::LeftBrace)) {
             self.begin_scope();
             let v = self.block()?;
             self.end_scope();
             Ok(v)
+        } else if self.tag(Some(TokenKind::For)) {
+            self.for_statement()
+        } else if self.tag(Some(TokenKind::Return)) {
+            self.return_statement()
         } else {
             self.expression_stmt()
         }
     }
 
+    /// Parse a for loop
+    /// i.e. parse `for x in xs { ... }`
+    pub(crate) fn for_statement(&mut self) -> CompilerResult<u8> {
+        self.consume(Some(TokenKind::Identifier), "Expected loop variable after 'for'.")?;
+        let name = self.lexer.slice().to_string();
+        self.consume(Some(TokenKind::In), "Expected 'in' after loop variable.")?;
+
+        let iterable = self.expression()?;
+        let iter = self.use_register()?;
+        self.emit_byte(Instruction::Iter, vec![iterable, iter])?;
+        self.free_register(iterable);
+
+        self.begin_scope();
+        let slot = self.add_local(name, false);
+        let value = self.use_register()?;
+        // NEXT and the jump out of the loop that it skips must share a bag
+        self.reserve(6);
+        let start = self.position();
+        self.emit_byte(Instruction::Next, vec![iter, value])?;
+        let exit = self.emit_jump((0, 0))?;
+        self.define_variable(slot, value)?;
+
+        self.consume(Some(TokenKind::LeftBrace), "Expected '{' before loop body.")?;
+        self.block()?;
+        self.emit_jump(start)?;
+        self.end_scope();
+
+        let end = self.position();
+        self.patch_jump(exit, end);
+        self.free_register(iter);
+        Ok(0)
+    }
+
     pub(crate) fn expression_stmt(&mut self) -> CompilerResult<u8> {
         let res = self.expression()?;
         self.consume(
             Some(TokenKind::Semicolon),
             "Expected ';' at end of expression",
         )?;
+        // The value of an expression statement is discarded, unless it is the program's value
+        if self.scope.depth == 0 && self.functions == 0 {
+            self.result = Some(res);
+        }
+        self.free_register(res);
         Ok(res)
     }
 
     /// Parse expressions and generate bytecode
     /// Root method for parsing expressions
     pub(crate) fn expression(&mut self) -> CompilerResult<u8> {
-        self.equality()
+        self.range()
+    }
+
+    /// Parse a range expression
+    /// i.e. parse `x..y`
+    pub(crate) fn range(&mut self) -> CompilerResult<u8> {
+        let start = self.equality()?;
+        if self.tag(Some(TokenKind::DotDot)) {
+            let end = self.equality()?;
+            let store = self.use_register()?;
+            self.emit_byte(Instruction::Range, vec![start, end, store])?;
+            self.free_register(start);
+            self.free_register(end);
+            Ok(store)
+        } else {
+            Ok(start)
+        }
     }
 
     /// Parse an equality assertion expression.
@@ -226,7 +522,7 @@ impl<'s> Compiler<'s> {
     pub(crate) fn equality(&mut self) -> CompilerResult<u8> {
         self.binop(
             Self::comparison,
-            false,
+            true,
             vec![
                 (TokenKind::EqualEqual, Instruction::Eq, false),
                 (TokenKind::BangEqual, Instruction::Ne, false),
@@ -239,7 +535,7 @@ impl<'s> Compiler<'s> {
     pub(crate) fn comparison(&mut self) -> CompilerResult<u8> {
         self.binop(
             Self::term,
-            false,
+            true,
             vec![
                 (TokenKind::Less, Instruction::Lt, false),
                 (TokenKind::Greater, Instruction::Lt, true),
@@ -278,23 +574,97 @@ impl<'s> Compiler<'s> {
     /// Parse a unary expression
     /// i.e. parse `!x` or `-x`
     pub(crate) fn unary(&mut self) -> CompilerResult<u8> {
-        let unary_ops = vec![
+        let unary_ops = [
             (TokenKind::Minus, Instruction::Neg),
             (TokenKind::Bang, Instruction::Not),
         ];
         Ok(
             if let Some(idx) = self.tag_any(unary_ops.iter().map(|i| i.0.clone()).collect()) {
-                let rhs = self.primitive()?;
+                let rhs = self.call()?;
 				let store = self.use_register()?;
                 self.emit_byte(unary_ops[idx].1, vec![rhs, store])?;
                 self.free_register(rhs);
                 store
             } else {
-                self.primitive()?
+                self.call()?
             },
         )
     }
 
+    /// Parse a call or property access expression
+    /// i.e. parse `f(x, y)`, `greet(name: "x")`, `s.upper()` or `ctx.path`
+    /// The positional arguments are collected into a list and the named arguments into a map.
+    /// A method's receiver is passed as its first positional argument.
+    pub(crate) fn call(&mut self) -> CompilerResult<u8> {
+        let mut callee = self.primitive()?;
+        loop {
+            let method = if self.tag(Some(TokenKind::Dot)) {
+                self.consume(Some(TokenKind::Identifier), "Expected property or method name after '.'.")?;
+                let name = self.emit_const(Value::VString(self.lexer.slice().to_string()))?;
+                if !self.tag(Some(TokenKind::LeftParen)) {
+                    let store = self.use_register()?;
+                    self.emit_byte(Instruction::Get, vec![callee, name, store])?;
+                    self.free_register(callee);
+                    self.free_register(name);
+                    callee = store;
+                    continue;
+                }
+                Some(name)
+            } else if self.tag(Some(TokenKind::LeftParen)) {
+                None
+            } else {
+                break;
+            };
+
+            let positional = self.use_register()?;
+            let named = self.use_register()?;
+            self.emit_byte(Instruction::List, vec![positional])?;
+            self.emit_byte(Instruction::Map, vec![named])?;
+            if method.is_some() {
+                self.emit_byte(Instruction::Push, vec![positional, callee])?;
+            }
+            self.arguments(positional, named)?;
+
+            let store = self.use_register()?;
+            match method {
+                Some(method) => {
+                    self.emit_byte(Instruction::Invoke, vec![method, positional, named, store])?;
+                    self.free_register(method);
+                }
+                None => self.emit_byte(Instruction::Call, vec![callee, positional, named, store])?,
+            }
+            self.free_register(callee);
+            self.free_register(positional);
+            self.free_register(named);
+            callee = store;
+        }
+        Ok(callee)
+    }
+
+    /// Parse the arguments of a call, after the opening parenthesis
+    pub(crate) fn arguments(&mut self, positional: u8, named: u8) -> CompilerResult {
+        while !self.tag(Some(TokenKind::RightParen)) {
+            if self.peek() == Some(TokenKind::Identifier) && self.peek_second() == Some(TokenKind::Colon) {
+                self.next();
+                let key = self.emit_const(Value::VString(self.lexer.slice().to_string()))?;
+                self.next();
+                let value = self.expression()?;
+                self.emit_byte(Instruction::Insert, vec![named, key, value])?;
+                self.free_register(key);
+                self.free_register(value);
+            } else {
+                let value = self.expression()?;
+                self.emit_byte(Instruction::Push, vec![positional, value])?;
+                self.free_register(value);
+            }
+            if !self.tag(Some(TokenKind::Comma)) {
+                self.consume(Some(TokenKind::RightParen), "Expected ')' after arguments.")?;
+                break;
+            }
+        }
+        Ok(())
+    }
+
     /// Parse a grouping (stuff in parentheses) expression
     pub(crate) fn grouping(&mut self) -> CompilerResult<u8> {
         let idx = self.expression()?;
@@ -320,30 +690,139 @@ impl<'s> Compiler<'s> {
         let n = next.unwrap();
         use TokenKind::*;
         // Check if the token was a primitive datatype
-        let res = match n {
+        match n {
             Number(n) => self.emit_const(Value::VNumber(n)),
             Bool(b) => self.emit_const(Value::VBool(b)),
+            Str => self.string(),
+            Nil => self.emit_const(Value::VNil),
             Identifier => self.load_variable(),
             LeftParen => self.grouping(),
+            LeftBracket => self.list(),
+            LeftBrace => self.map(),
             _ => Err(CompilerError::TokenError(TokenError::EarlyEof))
 
+        }
+    }
+
+    /// Compile a string literal
+    /// Interpolated strings are lowered into a chain of [Instruction::Concat]s, starting from the
+    /// leading text (or an empty string)
+    pub(crate) fn string(&mut self) -> CompilerResult<u8> {
+        let start = self.lexer.span().start + 1;
+        // The lexer has already checked that the literal is well formed
+        let (parts, _) = string_parts(&self.lexer.source()[start..], start)
+            .ok_or(CompilerError::TokenError(TokenError::EarlyEof))?;
+        let mut parts = parts.into_iter().peekable();
+        let mut store = match parts.next_if(|p| matches!(p, StrPart::Text(_))) {
+            Some(StrPart::Text(text)) => self.emit_const(Value::VString(text))?,
+            _ => self.emit_const(Value::VString(String::new()))?,
         };
+        for part in parts {
+            let rhs = match part {
+                StrPart::Text(text) => self.emit_const(Value::VString(text))?,
+                StrPart::Expr(start, end) => self.interpolation(start, end)?,
+            };
+            let out = self.use_register()?;
+            self.emit_byte(Instruction::Concat, vec![store, rhs, out])?;
+            self.free_register(store);
+            self.free_register(rhs);
+            store = out;
+        }
+        Ok(store)
+    }
+
+    /// Compile the expression interpolated into a string at `start..end` in the source
+    pub(crate) fn interpolation(&mut self, start: usize, end: usize) -> CompilerResult<u8> {
+        let source = self.lexer.source();
+        let outer = std::mem::replace(&mut self.lexer, TokenKind::lexer(&source[start..end]));
+        let res = self.expression().and_then(|r| {
+            self.consume(None, "Expected '}' after interpolated expression.")?;
+            Ok(r)
+        });
+        self.lexer = outer;
         res
     }
 
+    /// Compile a list literal
+    /// i.e. parse `[x, y, z]`
+    pub(crate) fn list(&mut self) -> CompilerResult<u8> {
+        let store = self.use_register()?;
+        self.emit_byte(Instruction::List, vec![store])?;
+        while !self.tag(Some(TokenKind::RightBracket)) {
+            let item = self.expression()?;
+            self.emit_byte(Instruction::Push, vec![store, item])?;
+            self.free_register(item);
+            if !self.tag(Some(TokenKind::Comma)) {
+                self.consume(Some(TokenKind::RightBracket), "Expected ']' after list items.")?;
+                break;
+            }
+        }
+        Ok(store)
+    }
+
+    /// Compile a map literal
+    /// i.e. parse `{"x": 1, "y": 2}`
+    pub(crate) fn map(&mut self) -> CompilerResult<u8> {
+        let store = self.use_register()?;
+        self.emit_byte(Instruction::Map, vec![store])?;
+        while !self.tag(Some(TokenKind::RightBrace)) {
+            let key = self.expression()?;
+            self.consume(Some(TokenKind::Colon), "Expected ':' after map key.")?;
+            let value = self.expression()?;
+            self.emit_byte(Instruction::Insert, vec![store, key, value])?;
+            self.free_register(key);
+            self.free_register(value);
+            if !self.tag(Some(TokenKind::Comma)) {
+                self.consume(Some(TokenKind::RightBrace), "Expected '}' after map entries.")?;
+                break;
+            }
+        }
+        Ok(store)
+    }
+
     pub(crate) fn load_variable(&mut self) -> CompilerResult<u8> {
-        let idx = self.ident_const()?;
+        let name = self.lexer.slice().to_string();
+        let binding = match self.resolve(&name) {
+            Err(_) if stdlib::module(&name).is_some() => return self.module_member(&name),
+            binding => binding?,
+        };
+        let idx = match binding {
+            Binding::Local(idx) => idx,
+            Binding::Const { value, .. } => {
+                if self.peek() == Some(TokenKind::Equal) {
+                    return Err(CompilerError::ScopeError(ScopeError::Immutable(name)));
+                }
+                return self.emit_const(value);
+            }
+        };
         if self.tag(Some(TokenKind::Equal)) {
+            if !self.scope.vars[idx as usize].mutable {
+                return Err(CompilerError::ScopeError(ScopeError::Immutable(name)));
+            }
             let value = self.expression()?;
             self.emit_byte(Instruction::Set, vec![idx, value])?;
+            self.free_register(value);
         }
         let store = self.use_register()?;
         self.emit_byte(Instruction::Read, vec![idx, store])?;
         Ok(store)
     }
 
+    /// Compile an access to a standard library module's member
+    /// i.e. parse `math.sqrt` or `math.pi`
+    pub(crate) fn module_member(&mut self, module: &str) -> CompilerResult<u8> {
+        self.consume(Some(TokenKind::Dot), "Expected '.' after module name.")?;
+        self.consume(Some(TokenKind::Identifier), "Expected member name after '.'.")?;
+        let name = format!("{}.{}", module, self.lexer.slice());
+        let member = stdlib::module(module).and_then(|m| m.member(self.lexer.slice()));
+        match member {
+            Some(Member::Function(_)) => self.emit_const(Value::VNative(name)),
+            Some(Member::Constant(value)) => self.emit_const(value),
+            None => Err(CompilerError::ScopeError(ScopeError::UndefinedVariable(name))),
+        }
+    }
+
     pub(crate) fn block(&mut self) -> CompilerResult<u8> {
-		self.scope.depth+=1;
         while self.peek() != Some(TokenKind::RightBrace) && !self.tag(None) {
             self.declaration()?;
         }
@@ -357,31 +836,62 @@ impl<'s> Compiler<'s> {
 
     pub(crate) fn end_scope(&mut self) {
         self.scope.depth -= 1;
+        // Variables declared in the scope are no longer visible, but keep their slots
+        let vars = &self.scope.vars;
+        let depth = self.scope.depth;
+        self.locals.retain(|b| match b {
+            Binding::Local(i) => vars[*i as usize].depth <= depth,
+            Binding::Const { depth: d, .. } => *d <= depth,
+        });
     }
 
-    /// Parse a variable and produce a global index
-    pub(crate) fn parse_variable(&mut self, why: &'static str) -> CompilerResult<u8> {
+    /// Parse a variable and produce its index in the scope
+    pub(crate) fn parse_variable(&mut self, why: &'static str, mutable: bool) -> CompilerResult<u8> {
         self.consume(Some(TokenKind::Identifier), why)?;
 
-        self.declare_variable();
+        Ok(self.declare_variable(mutable))
+    }
 
-        self.ident_const()
+    pub(crate) fn declare_variable(&mut self, mutable: bool) -> u8 {
+        self.add_local(self.lexer.slice().to_string(), mutable)
     }
 
-    pub(crate) fn declare_variable(&mut self) {
+    /// Allocate a slot for a variable in the current scope and return its index
+    pub(crate) fn add_local(&mut self, name: String, mutable: bool) -> u8 {
+        let idx = self.scope.vars.len() as u8;
         self.scope.num_vars += 1;
         self.scope.vars.push(Local {
-            name: self.lexer.slice().to_string(),
+            name,
             depth: self.scope.depth,
+            mutable,
         });
+        self.locals.push(Binding::Local(idx));
+        idx
     }
 
-    pub(crate) fn ident_const(&mut self) -> CompilerResult<u8> {
-        self.emit_const(Value::VString(self.lexer.slice().to_string()))
+    /// Find the innermost visible variable or constant called `name`
+    /// Native functions are treated as constants in the outermost scope, so variables can shadow them
+    pub(crate) fn resolve(&self, name: &str) -> CompilerResult<Binding> {
+        let native = || self.natives.iter().find(|n| *n == name).map(|n| Binding::Const {
+            name: n.clone(),
+            value: Value::VNative(n.clone()),
+            depth: 0,
+        });
+        self.locals
+            .iter()
+            .rev()
+            .find(|b| match b {
+                Binding::Local(i) => self.scope.vars[*i as usize].name == name,
+                Binding::Const { name: n, .. } => n == name,
+            })
+            .cloned()
+            .or_else(native)
+            .ok_or_else(|| CompilerError::ScopeError(ScopeError::UndefinedVariable(name.into())))
     }
 
-    pub(crate) fn define_variable(&mut self, ident_idx: u8, value_idx: u8) -> CompilerResult {
-        self.emit_byte(Instruction::Let, vec![ident_idx, value_idx])?;
+    pub(crate) fn define_variable(&mut self, local_idx: u8, value_idx: u8) -> CompilerResult {
+        self.emit_byte(Instruction::Let, vec![local_idx, value_idx])?;
+        self.free_register(value_idx);
         Ok(())
     }
 
@@ -394,11 +904,11 @@ impl<'s> Compiler<'s> {
         expected: Vec<(TokenKind, Instruction, bool)>,
     ) -> CompilerResult<u8> {
         // Get the left hand side register idx
-        let lhs = next(self)? as u8;
+        let lhs = next(self)?;
         // Check if the next token is any of the expected operators
         if let Some(idx) = self.tag_any(expected.iter().map(|i| i.0.clone()).collect()) {
             // Get the right hand side register idx
-            let rhs = next(self)? as u8;
+            let rhs = next(self)?;
             let mut args = if expected[idx].2 {
                 vec![rhs, lhs]
             } else {
@@ -435,7 +945,7 @@ mod tests {
 
         /// Init a compiler instance
         #[inline]
-        pub(super) fn compiler(source: &str) -> Compiler {
+        pub(super) fn compiler(source: &str) -> Compiler<'_> {
             // Create an instance, use default values as they are not necessary for testing (yet)
             let mut compiler = Compiler {
                 lexer: TokenKind::lexer(source),
@@ -488,17 +998,19 @@ mod tests {
 
     #[test]
     fn consume() {
-        let mut compiler = Compiler::default();
-        compiler.lexer = TokenKind::lexer(";;;;;;");
+        let mut compiler = Compiler {
+            lexer: TokenKind::lexer(";;;;;;"),
+            ..Default::default()
+        };
         assert!(compiler.consume(Some(TokenKind::Semicolon), "").is_ok());
     }
 
     #[test]
     fn comparison() {
-        utils::binexp_test("<", Instruction::Lt, false, false);
-        utils::binexp_test(">", Instruction::Lt, true, false);
-        utils::binexp_test("<=", Instruction::Le, false, false);
-        utils::binexp_test(">=", Instruction::Le, true, false);
+        utils::binexp_test("<", Instruction::Lt, false, true);
+        utils::binexp_test(">", Instruction::Lt, true, true);
+        utils::binexp_test("<=", Instruction::Le, false, true);
+        utils::binexp_test(">=", Instruction::Le, true, true);
     }
 
     #[test]
@@ -536,20 +1048,134 @@ mod tests {
     fn let_declaration() {
         let compiler = compiler("let asdf = true;");
         let mut bag = Bag::new();
-		assert!(bag.emit_const(&Value::VString("asdf".into()), 0).is_ok());
-		assert!(bag.emit_const(&Value::VBool(true), 1).is_ok());
+		assert!(bag.emit_const(&Value::VBool(true), 0).is_ok());
 
         let scope = super::CompilerScope {
             vars: vec![super::Local {
                 name: "asdf".to_string(),
                 depth: 0,
+                mutable: false,
             }],
             num_vars: 1,
             depth: 0,
         };
 
-		assert!(bag.emit_byte(Instruction::Let, &vec![0, 1]).is_ok());
+		assert!(bag.emit_byte(Instruction::Let, &vec![0, 0]).is_ok());
 		assert_eq!(compiler.baggage, vec![bag.zip_up()]);
         assert_eq!(compiler.scope, scope);
     }
+
+    #[test]
+    fn list() {
+        let compiler = compiler("[1, true];");
+
+        let mut bag = Bag::new();
+		assert!(bag.emit_byte(Instruction::List, &vec![0]).is_ok());
+		assert!(bag.emit_const(&Value::VNumber(1.), 1).is_ok());
+		assert!(bag.emit_byte(Instruction::Push, &vec![0, 1]).is_ok());
+		assert!(bag.emit_const(&Value::VBool(true), 2).is_ok());
+		assert!(bag.emit_byte(Instruction::Push, &vec![0, 2]).is_ok());
+        assert_eq!(compiler.baggage, vec![bag.zip_up()]);
+    }
+
+    #[test]
+    fn for_loop() {
+        let compiler = compiler("for x in 0..2 {}");
+
+        let mut bag = Bag::new();
+		assert!(bag.emit_const(&Value::VNumber(0.), 0).is_ok());
+		assert!(bag.emit_const(&Value::VNumber(2.), 1).is_ok());
+		assert!(bag.emit_byte(Instruction::Range, &vec![0, 1, 2]).is_ok());
+		assert!(bag.emit_byte(Instruction::Iter, &vec![2, 3]).is_ok());
+		let start = bag.bytecode.len() as u8;
+		assert!(bag.emit_byte(Instruction::Next, &vec![3, 4]).is_ok());
+		assert!(bag.emit_byte(Instruction::Jump, &vec![0, start + 12]).is_ok());
+		assert!(bag.emit_byte(Instruction::Let, &vec![0, 4]).is_ok());
+		assert!(bag.emit_byte(Instruction::Jump, &vec![0, start]).is_ok());
+        assert_eq!(compiler.baggage, vec![bag.zip_up()]);
+    }
+
+    #[test]
+    fn undefined_variable() {
+        let mut compiler = Compiler::new("{ let x = 1; } x;");
+        assert_eq!(
+            compiler.compile(),
+            Err(super::CompilerError::ScopeError(super::ScopeError::UndefinedVariable("x".into())))
+        );
+    }
+
+    #[test]
+    fn type_checked() {
+        let mut compiler = Compiler::new("let x: num = 1;\ntrue + x;");
+        assert!(matches!(
+            compiler.compile(),
+            Err(super::CompilerError::TypeError(super::TypeError::Mismatch { .. }))
+        ));
+        // Nothing is emitted for programs that fail to type check
+        assert!(compiler.baggage.is_empty());
+    }
+
+    #[test]
+    fn immutable() {
+        use super::{CompilerError, ScopeError};
+        let immutable = |source| Compiler::new(source).compile();
+        assert_eq!(immutable("let x = 1; x = 2;"), Err(CompilerError::ScopeError(ScopeError::Immutable("x".into()))));
+        assert_eq!(immutable("for i in 0..2 { i = 1; }"), Err(CompilerError::ScopeError(ScopeError::Immutable("i".into()))));
+        assert_eq!(immutable("const X = 1; X = 2;"), Err(CompilerError::ScopeError(ScopeError::Immutable("X".into()))));
+        assert_eq!(immutable("let mut x = 1; x = 2;"), Ok(()));
+    }
+
+    #[test]
+    fn constant_inlined() {
+        let compiler = compiler("const X = 4; const Y = X; Y;");
+
+        let mut bag = Bag::new();
+		assert!(bag.emit_const(&Value::VNumber(4.), 0).is_ok());
+        assert_eq!(compiler.baggage, vec![bag.zip_up()]);
+        assert!(compiler.scope.vars.is_empty());
+
+        let mut compiler = Compiler::new("let x = 1; const Y = x;");
+        assert_eq!(
+            compiler.compile(),
+            Err(super::CompilerError::ScopeError(super::ScopeError::NotConstant("Y".into())))
+        );
+    }
+
+    #[test]
+    fn return_outside_function() {
+        let mut compiler = Compiler::new("fn f() { return 1; } return 2;");
+        assert_eq!(
+            compiler.compile(),
+            Err(super::CompilerError::ScopeError(super::ScopeError::ReturnOutsideFunction))
+        );
+    }
+
+    #[test]
+    fn duplicate_parameters() {
+        for source in ["fn f(a, a) { return a; } f(1, 2);", "fn f(a, b = 1, ...a) {}"] {
+            assert_eq!(
+                Compiler::new(source).compile(),
+                Err(super::CompilerError::ScopeError(super::ScopeError::DuplicateParameter {
+                    function: "f".into(),
+                    name: "a".into(),
+                }))
+            );
+        }
+        assert!(Compiler::new("fn f(a, b) {} fn g(a, b) {}").compile().is_ok());
+    }
+
+    #[test]
+    fn native_names() {
+        let mut compiler = Compiler::new("log = 1;");
+        compiler.declare_native("log");
+        assert_eq!(
+            compiler.compile(),
+            Err(super::CompilerError::ScopeError(super::ScopeError::Immutable("log".into())))
+        );
+        let mut compiler = Compiler::new("log(1);");
+        assert_eq!(
+            compiler.compile(),
+            Err(super::CompilerError::ScopeError(super::ScopeError::UndefinedVariable("log".into())))
+        );
+    }
 }
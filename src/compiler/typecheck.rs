@@ -0,0 +1,646 @@
+/*
+	# Type Checker
+	A separate pass over the source which runs before any bytecode is emitted. It follows the same grammar
+	as the compiler, but instead of emitting instructions each parser function returns the [Type] of the
+	expression it parsed, along with where the expression started.
+
+	Typing is gradual: literals have the type you would expect, variables have the type they are annotated
+	with (`let x: num = 1;`) and everything else is [Type::Any], which is compatible with every type.
+	Code without annotations is only checked as far as its literals go, and otherwise stays dynamically
+	typed.
+*/
+
+use std::fmt;
+
+use crate::{TokenKind, scanner::{string_parts, StrPart}, error::compiler::*};
+use logos::{Lexer, Logos};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+	Num,
+	Str,
+	Bool,
+	Nil,
+	List,
+	Map,
+	Range,
+	Fn,
+	Any,
+}
+
+impl Type {
+	/// Look up a type by the name it is annotated with
+	pub fn from_name(name: &str) -> Option<Self> {
+		Some(match name {
+			"num" => Self::Num,
+			"str" => Self::Str,
+			"bool" => Self::Bool,
+			"nil" => Self::Nil,
+			"list" => Self::List,
+			"map" => Self::Map,
+			"range" => Self::Range,
+			"fn" => Self::Fn,
+			"any" => Self::Any,
+			_ => return None,
+		})
+	}
+
+	/// Whether a value of type `other` can be used where `self` is expected
+	pub fn accepts(self, other: Type) -> bool {
+		self == Self::Any || other == Self::Any || self == other
+	}
+}
+
+impl fmt::Display for Type {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let name = match self {
+			Self::Num => "num",
+			Self::Str => "str",
+			Self::Bool => "bool",
+			Self::Nil => "nil",
+			Self::List => "list",
+			Self::Map => "map",
+			Self::Range => "range",
+			Self::Fn => "fn",
+			Self::Any => "any",
+		};
+		write!(f, "{}", name)
+	}
+}
+
+/// The type of an expression and the offset in the source that it started at
+type Typed = (Type, usize);
+
+/// A variable that is visible to the checker
+#[derive(Clone, Debug, PartialEq)]
+pub struct Var {
+	pub name: String,
+	pub ty: Type,
+	pub depth: u8,
+	/// The signature of a function declared with `fn`, which can't be reassigned
+	pub signature: Option<Signature>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Signature {
+	/// The name and type of each parameter, and whether it is a rest parameter
+	pub params: Vec<(String, Type, bool)>,
+	pub returns: Type,
+}
+
+#[derive(Clone)]
+pub struct TypeChecker<'src> {
+	pub source: &'src str,
+	pub lexer: Lexer<'src, TokenKind>,
+	/// Offset of the lexer's source in `source`, for checking interpolated expressions
+	pub offset: usize,
+	pub vars: Vec<Var>,
+	pub depth: u8,
+	/// The return type of each function that is being checked, innermost last
+	pub returns: Vec<Type>,
+}
+
+impl<'src> TypeChecker<'src> {
+	pub fn new(source: &'src str) -> Self {
+		Self {
+			source,
+			lexer: TokenKind::lexer(source),
+			offset: 0,
+			vars: vec![],
+			depth: 0,
+			returns: vec![],
+		}
+	}
+
+	/// Check the whole program
+	pub fn check(&mut self) -> CompilerResult {
+		while self.peek().is_some() {
+			self.declaration()?;
+		}
+		Ok(())
+	}
+
+	fn next(&mut self) -> Option<TokenKind> {
+		self.lexer.next()
+	}
+
+	fn peek(&self) -> Option<TokenKind> {
+		self.lexer.clone().next()
+	}
+
+	fn tag(&mut self, expected: TokenKind) -> bool {
+		if self.peek() == Some(expected) {
+			self.next();
+			true
+		} else {
+			false
+		}
+	}
+
+	fn consume(&mut self, kind: Option<TokenKind>, why: &'static str) -> CompilerResult {
+		match self.next() {
+			k if k == kind => Ok(()),
+			recieved => Err(CompilerError::TokenError(
+				TokenError::ExpectedToken { reason: why, expected: kind, recieved }
+			)),
+		}
+	}
+
+	/// The offset in the source of the token that was just consumed
+	fn here(&self) -> usize {
+		self.offset + self.lexer.span().start
+	}
+
+	/// Turn an offset in the source into a line and column
+	pub fn location(&self, at: usize) -> Location {
+		let before = &self.source[..at];
+		let line = before.matches('\n').count() + 1;
+		let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+		Location { line, column }
+	}
+
+	/// Make sure that `found` can be used where `expected` is
+	fn expect(&self, expected: Type, found: Typed) -> CompilerResult {
+		if expected.accepts(found.0) {
+			Ok(())
+		} else {
+			Err(CompilerError::TypeError(TypeError::Mismatch {
+				expected,
+				found: found.0,
+				at: self.location(found.1),
+			}))
+		}
+	}
+
+	/// Parse a type annotation
+	/// i.e. parse the `num` in `let x: num = 1;`
+	pub(crate) fn annotation(&mut self) -> CompilerResult<Type> {
+		match self.next() {
+			Some(TokenKind::Identifier) => {
+				let name = self.lexer.slice();
+				Type::from_name(name).ok_or_else(|| CompilerError::TypeError(TypeError::UnknownType {
+					name: name.into(),
+					at: self.location(self.here()),
+				}))
+			}
+			// `fn` and `nil` are keywords rather than identifiers
+			Some(TokenKind::Fn) => Ok(Type::Fn),
+			Some(TokenKind::Nil) => Ok(Type::Nil),
+			recieved => Err(CompilerError::TokenError(TokenError::ExpectedToken {
+				reason: "Expected a type after ':'.",
+				expected: Some(TokenKind::Identifier),
+				recieved,
+			})),
+		}
+	}
+
+	fn declare(&mut self, name: String, ty: Type) {
+		self.vars.push(Var {
+			name,
+			ty,
+			depth: self.depth,
+			signature: None,
+		});
+	}
+
+	/// The type of a variable. Variables the checker doesn't know about are left for the compiler to
+	/// report.
+	fn resolve(&self, name: &str) -> Type {
+		self.vars
+			.iter()
+			.rev()
+			.find(|v| v.name == name)
+			.map(|v| v.ty)
+			.unwrap_or(Type::Any)
+	}
+
+	fn signature(&self, name: &str) -> Option<Signature> {
+		self.vars.iter().rev().find(|v| v.name == name)?.signature.clone()
+	}
+
+	fn begin_scope(&mut self) {
+		self.depth += 1;
+	}
+
+	fn end_scope(&mut self) {
+		self.depth -= 1;
+		let depth = self.depth;
+		self.vars.retain(|v| v.depth <= depth);
+	}
+
+	fn declaration(&mut self) -> CompilerResult {
+		if self.tag(TokenKind::Let) {
+			self.let_declaration()
+		} else if self.tag(TokenKind::Const) {
+			self.const_declaration()
+		} else if self.tag(TokenKind::Fn) {
+			self.fn_declaration()
+		} else {
+			self.statement()
+		}
+	}
+
+	/// Check a function declaration against its annotations, e.g. `fn add(a: num, b: num) -> num`.
+	/// Unannotated parameters and return values are [Type::Any].
+	fn fn_declaration(&mut self) -> CompilerResult {
+		self.consume(Some(TokenKind::Identifier), "Expected function name after 'fn'.")?;
+		let name = self.lexer.slice().to_string();
+		// Declared before the body so that the function can call itself, once the signature is known
+		let idx = self.vars.len();
+		self.declare(name, Type::Fn);
+
+		self.begin_scope();
+		self.consume(Some(TokenKind::LeftParen), "Expected '(' after function name.")?;
+		let mut params = vec![];
+		while !self.tag(TokenKind::RightParen) {
+			let rest = self.tag(TokenKind::Ellipsis);
+			self.consume(Some(TokenKind::Identifier), "Expected parameter name.")?;
+			let name = self.lexer.slice().to_string();
+			let ty = if rest {
+				Type::List
+			} else if self.tag(TokenKind::Colon) {
+				self.annotation()?
+			} else {
+				Type::Any
+			};
+			if !rest && self.tag(TokenKind::Equal) {
+				let default = self.expression()?;
+				self.expect(ty, default)?;
+			}
+			self.declare(name.clone(), ty);
+			params.push((name, ty, rest));
+			if !self.tag(TokenKind::Comma) {
+				self.consume(Some(TokenKind::RightParen), "Expected ')' after parameters.")?;
+				break;
+			}
+		}
+		let returns = if self.tag(TokenKind::Arrow) {
+			self.annotation()?
+		} else {
+			Type::Any
+		};
+		self.vars[idx].signature = Some(Signature { params, returns });
+
+		self.returns.push(returns);
+		self.consume(Some(TokenKind::LeftBrace), "Expected '{' before function body.")?;
+		self.block()?;
+		self.returns.pop();
+		self.end_scope();
+		Ok(())
+	}
+
+	fn return_statement(&mut self) -> CompilerResult {
+		let at = self.here();
+		let value = if self.peek() == Some(TokenKind::Semicolon) {
+			(Type::Nil, at)
+		} else {
+			self.expression()?
+		};
+		if let Some(&returns) = self.returns.last() {
+			self.expect(returns, value)?;
+		}
+		self.consume(Some(TokenKind::Semicolon), "Expected ';' after return value.")
+	}
+
+	fn let_declaration(&mut self) -> CompilerResult {
+		self.tag(TokenKind::Mut);
+		self.consume(Some(TokenKind::Identifier), "Expected variable name after 'let'.")?;
+		let name = self.lexer.slice().to_string();
+		let annotation = if self.tag(TokenKind::Colon) {
+			Some(self.annotation()?)
+		} else {
+			None
+		};
+
+		self.consume(Some(TokenKind::Equal), "Variables must be initialized.")?;
+		let value = self.expression()?;
+		if let Some(ty) = annotation {
+			self.expect(ty, value)?;
+		}
+		self.consume(Some(TokenKind::Semicolon), "Expected ';' after variable declaration")?;
+		self.declare(name, annotation.unwrap_or(Type::Any));
+		Ok(())
+	}
+
+	/// Constants can never change, so unlike variables they keep the type of their value
+	fn const_declaration(&mut self) -> CompilerResult {
+		self.consume(Some(TokenKind::Identifier), "Expected constant name after 'const'.")?;
+		let name = self.lexer.slice().to_string();
+		let annotation = if self.tag(TokenKind::Colon) {
+			Some(self.annotation()?)
+		} else {
+			None
+		};
+
+		self.consume(Some(TokenKind::Equal), "Constants must be initialized.")?;
+		let value = self.expression()?;
+		if let Some(ty) = annotation {
+			self.expect(ty, value)?;
+		}
+		self.consume(Some(TokenKind::Semicolon), "Expected ';' after constant declaration")?;
+		self.declare(name, annotation.unwrap_or(value.0));
+		Ok(())
+	}
+
+	fn statement(&mut self) -> CompilerResult {
+		if self.tag(TokenKind::LeftBrace) {
+			self.begin_scope();
+			self.block()?;
+			self.end_scope();
+		} else if self.tag(TokenKind::For) {
+			self.for_statement()?;
+		} else if self.tag(TokenKind::Return) {
+			self.return_statement()?;
+		} else {
+			self.expression()?;
+			self.consume(Some(TokenKind::Semicolon), "Expected ';' at end of expression")?;
+		}
+		Ok(())
+	}
+
+	fn block(&mut self) -> CompilerResult {
+		while self.peek() != Some(TokenKind::RightBrace) && self.peek().is_some() {
+			self.declaration()?;
+		}
+		self.consume(Some(TokenKind::RightBrace), "Expect '}' after block.")
+	}
+
+	fn for_statement(&mut self) -> CompilerResult {
+		self.consume(Some(TokenKind::Identifier), "Expected loop variable after 'for'.")?;
+		let name = self.lexer.slice().to_string();
+		self.consume(Some(TokenKind::In), "Expected 'in' after loop variable.")?;
+		self.expression()?;
+
+		self.begin_scope();
+		// Loop variables aren't annotated, so they're dynamically typed like any other variable
+		self.declare(name, Type::Any);
+		self.consume(Some(TokenKind::LeftBrace), "Expected '{' before loop body.")?;
+		self.block()?;
+		self.end_scope();
+		Ok(())
+	}
+
+	fn expression(&mut self) -> CompilerResult<Typed> {
+		self.range()
+	}
+
+	fn range(&mut self) -> CompilerResult<Typed> {
+		let start = self.equality()?;
+		if self.tag(TokenKind::DotDot) {
+			let end = self.equality()?;
+			self.expect(Type::Num, start)?;
+			self.expect(Type::Num, end)?;
+			Ok((Type::Range, start.1))
+		} else {
+			Ok(start)
+		}
+	}
+
+	fn equality(&mut self) -> CompilerResult<Typed> {
+		let lhs = self.comparison()?;
+		if self.tag(TokenKind::EqualEqual) || self.tag(TokenKind::BangEqual) {
+			self.comparison()?;
+			Ok((Type::Bool, lhs.1))
+		} else {
+			Ok(lhs)
+		}
+	}
+
+	fn comparison(&mut self) -> CompilerResult<Typed> {
+		let lhs = self.term()?;
+		let ops = [TokenKind::Less, TokenKind::Greater, TokenKind::LessEqual, TokenKind::GreaterEqual];
+		if ops.into_iter().any(|op| self.tag(op)) {
+			self.term()?;
+			Ok((Type::Bool, lhs.1))
+		} else {
+			Ok(lhs)
+		}
+	}
+
+	fn term(&mut self) -> CompilerResult<Typed> {
+		self.arithmetic(Self::factor, [TokenKind::Plus, TokenKind::Minus])
+	}
+
+	fn factor(&mut self) -> CompilerResult<Typed> {
+		self.arithmetic(Self::unary, [TokenKind::Star, TokenKind::Slash])
+	}
+
+	/// Check an arithmetic expression, where both sides must be numbers
+	fn arithmetic(
+		&mut self,
+		next: fn(&mut Self) -> CompilerResult<Typed>,
+		ops: [TokenKind; 2],
+	) -> CompilerResult<Typed> {
+		let lhs = next(self)?;
+		if ops.into_iter().any(|op| self.tag(op)) {
+			let rhs = next(self)?;
+			self.expect(Type::Num, lhs)?;
+			self.expect(Type::Num, rhs)?;
+			Ok((Type::Num, lhs.1))
+		} else {
+			Ok(lhs)
+		}
+	}
+
+	fn unary(&mut self) -> CompilerResult<Typed> {
+		let expected = if self.tag(TokenKind::Minus) {
+			Type::Num
+		} else if self.tag(TokenKind::Bang) {
+			Type::Bool
+		} else {
+			return self.call();
+		};
+		let at = self.here();
+		let rhs = self.call()?;
+		self.expect(expected, rhs)?;
+		Ok((expected, at))
+	}
+
+	/// Check a call expression. Calls to functions declared with `fn` are checked against the
+	/// function's signature.
+	fn call(&mut self) -> CompilerResult<Typed> {
+		let mut lexer = self.lexer.clone();
+		let mut signature = match lexer.next() {
+			Some(TokenKind::Identifier) => {
+				let name = lexer.slice();
+				match lexer.next() {
+					Some(TokenKind::LeftParen) => self.signature(name),
+					_ => None,
+				}
+			}
+			_ => None,
+		};
+		let mut callee = self.primitive()?;
+		loop {
+			// Properties, module members and methods aren't typed
+			if self.tag(TokenKind::Dot) {
+				self.consume(Some(TokenKind::Identifier), "Expected member name after '.'.")?;
+				callee = (Type::Any, callee.1);
+				signature = None;
+				continue;
+			}
+			if !self.tag(TokenKind::LeftParen) {
+				break;
+			}
+			let mut position = 0;
+			while !self.tag(TokenKind::RightParen) {
+				let mut lexer = self.lexer.clone();
+				let param = if let (Some(TokenKind::Identifier), Some(TokenKind::Colon)) = (lexer.next(), lexer.next()) {
+					self.next();
+					let name = self.lexer.slice().to_string();
+					self.next();
+					signature.as_ref().and_then(|s| s.params.iter().find(|p| p.0 == name && !p.2)).map(|p| p.1)
+				} else {
+					position += 1;
+					signature.as_ref().and_then(|s| s.params.iter().filter(|p| !p.2).nth(position - 1)).map(|p| p.1)
+				};
+				let value = self.expression()?;
+				self.expect(param.unwrap_or(Type::Any), value)?;
+				if !self.tag(TokenKind::Comma) {
+					self.consume(Some(TokenKind::RightParen), "Expected ')' after arguments.")?;
+					break;
+				}
+			}
+			callee = (signature.take().map(|s| s.returns).unwrap_or(Type::Any), callee.1);
+		}
+		Ok(callee)
+	}
+
+	fn primitive(&mut self) -> CompilerResult<Typed> {
+		let next = self.next().ok_or(CompilerError::TokenError(TokenError::EarlyEof))?;
+		let at = self.here();
+		use TokenKind::*;
+		let ty = match next {
+			Number(_) => Type::Num,
+			Bool(_) => Type::Bool,
+			Nil => Type::Nil,
+			Str => {
+				self.string()?;
+				Type::Str
+			}
+			Identifier => {
+				let ty = self.resolve(self.lexer.slice());
+				if self.tag(Equal) {
+					let value = self.expression()?;
+					self.expect(ty, value)?;
+				}
+				ty
+			}
+			LeftParen => {
+				let ty = self.expression()?.0;
+				self.consume(Some(RightParen), "Expected ')' following expression.")?;
+				ty
+			}
+			LeftBracket => {
+				self.items(RightBracket, false)?;
+				Type::List
+			}
+			LeftBrace => {
+				self.items(RightBrace, true)?;
+				Type::Map
+			}
+			_ => return Err(CompilerError::TokenError(TokenError::EarlyEof)),
+		};
+		Ok((ty, at))
+	}
+
+	/// Check the items of a list or the entries of a map
+	fn items(&mut self, close: TokenKind, entries: bool) -> CompilerResult {
+		while !self.tag(close.clone()) {
+			if entries {
+				let key = self.expression()?;
+				self.expect(Type::Str, key)?;
+				self.consume(Some(TokenKind::Colon), "Expected ':' after map key.")?;
+			}
+			self.expression()?;
+			if !self.tag(TokenKind::Comma) {
+				return self.consume(Some(close), "Expected closing bracket after items.");
+			}
+		}
+		Ok(())
+	}
+
+	/// Check the expressions interpolated into the string literal that was just consumed
+	fn string(&mut self) -> CompilerResult {
+		let start = self.lexer.span().start + 1;
+		let (parts, _) = string_parts(&self.lexer.source()[start..], start)
+			.ok_or(CompilerError::TokenError(TokenError::EarlyEof))?;
+		for part in parts {
+			if let StrPart::Expr(start, end) = part {
+				let source = self.lexer.source();
+				let mut inner = Self {
+					lexer: TokenKind::lexer(&source[start..end]),
+					offset: self.offset + start,
+					..self.clone()
+				};
+				inner.expression()?;
+				inner.consume(None, "Expected '}' after interpolated expression.")?;
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn mismatch(expected: Type, found: Type, line: usize, column: usize) -> CompilerResult {
+		Err(CompilerError::TypeError(TypeError::Mismatch {
+			expected,
+			found,
+			at: Location { line, column },
+		}))
+	}
+
+	#[test]
+	fn arithmetic() {
+		assert_eq!(TypeChecker::new("1 + 2;").check(), Ok(()));
+		assert_eq!(TypeChecker::new("true + 1;").check(), mismatch(Type::Num, Type::Bool, 1, 1));
+		assert_eq!(TypeChecker::new("let x = 1;\n-\"a\";").check(), mismatch(Type::Num, Type::Str, 2, 2));
+	}
+
+	#[test]
+	fn annotations() {
+		assert_eq!(TypeChecker::new("let mut x: num = 1; x = 2;").check(), Ok(()));
+		assert_eq!(TypeChecker::new("let x: str = 1;").check(), mismatch(Type::Str, Type::Num, 1, 14));
+		assert_eq!(TypeChecker::new("let mut x: num = 1; x = \"a\";").check(), mismatch(Type::Num, Type::Str, 1, 25));
+		assert_eq!(
+			TypeChecker::new("let x: int = 1;").check(),
+			Err(CompilerError::TypeError(TypeError::UnknownType {
+				name: "int".into(),
+				at: Location { line: 1, column: 8 },
+			}))
+		);
+	}
+
+	#[test]
+	fn function_annotations() {
+		let source = "fn g(x) { return x; }";
+		assert_eq!(TypeChecker::new(&format!("{} let f: fn = g; let h: any = f;", source)).check(), Ok(()));
+		assert_eq!(TypeChecker::new(&format!("{} let f: num = g;", source)).check(), mismatch(Type::Num, Type::Fn, 1, 36));
+		assert_eq!(TypeChecker::new("let f: fn = 1;").check(), mismatch(Type::Fn, Type::Num, 1, 13));
+		assert_eq!(TypeChecker::new("fn k(f: fn) -> fn { return f; } k(1);").check(), mismatch(Type::Fn, Type::Num, 1, 35));
+		assert_eq!(crate::Engine::new().eval("fn g() { return 2; } let f: fn = g; f();"), Ok(crate::Value::VNumber(2.)));
+	}
+
+	#[test]
+	fn gradual() {
+		// Unannotated variables can hold anything
+		assert_eq!(TypeChecker::new("let mut x = true; x = 1; x + 1;").check(), Ok(()));
+		// Annotated variables are checked wherever they're used
+		assert_eq!(TypeChecker::new("let b: bool = true; b * 2;").check(), mismatch(Type::Num, Type::Bool, 1, 21));
+		assert_eq!(TypeChecker::new("\"{true - 1}\";").check(), mismatch(Type::Num, Type::Bool, 1, 3));
+	}
+
+	#[test]
+	fn signatures() {
+		let source = "fn add(a: num, b: num) -> num { return a + b; }";
+		assert_eq!(TypeChecker::new(&format!("{} add(1, 2) * 2;", source)).check(), Ok(()));
+		assert_eq!(TypeChecker::new(&format!("{} add(1, true);", source)).check(), mismatch(Type::Num, Type::Bool, 1, 56));
+		assert_eq!(TypeChecker::new(&format!("{} add(b: \"2\", a: 1);", source)).check(), mismatch(Type::Num, Type::Str, 1, 56));
+		assert_eq!(TypeChecker::new(&format!("{} !add(1, 2);", source)).check(), mismatch(Type::Bool, Type::Num, 1, 50));
+		assert_eq!(TypeChecker::new("fn f(x: str = 1) {}").check(), mismatch(Type::Str, Type::Num, 1, 15));
+		assert_eq!(TypeChecker::new("fn f() -> str { return; }").check(), mismatch(Type::Str, Type::Nil, 1, 17));
+	}
+}
@@ -0,0 +1,225 @@
+/*
+	# Engine
+	The engine is the simplest way to embed blush. It hides the compiler, bags and scopes behind three
+	calls: [Engine::compile] turns source code into a [Program], [Engine::run] runs it, and [Engine::eval]
+	does both.
+
+	The value of a program is the value of its final statement, if that statement is an expression at the
+	top level, otherwise it is nil:
+	```
+	let engine = blush::Engine::new();
+	assert_eq!(engine.eval("let x = 2; x * 3;"), Ok(blush::Value::VNumber(6.)));
+	```
+
+	## Callbacks
+	A program can also be loaded into the engine with [Engine::load], which runs it and keeps its runtime
+	around. The host can then call the functions it defined with [Engine::call], and the program's
+	variables keep their values from one call to the next.
+
+	## Threads
+	An engine is `Send + Sync`, so a server can share one engine and give each thread its own runtimes
+	with [Engine::run] or [Engine::runtime]. Natives are shared by all of those runtimes. The loaded
+	program can be called from any thread too, but only by one at a time.
+*/
+
+use std::{collections::HashMap, sync::{Arc, Mutex, PoisonError}};
+
+use crate::{Compiler, Program, Runtime, Value, error::{engine::*, runtime::RuntimeResult}, runtime::NativeFn};
+
+#[derive(Default)]
+pub struct Engine {
+	natives: HashMap<String, NativeFn>,
+	/// The runtime of the loaded program, behind a mutex so that the engine can be shared between threads
+	loaded: Option<Mutex<Runtime>>,
+}
+
+impl Engine {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Register a native function which every program run by the engine can call as `name`
+	pub fn register_native<F>(&mut self, name: impl Into<String>, function: F) -> &mut Self
+	where
+		F: Fn(&[Value]) -> RuntimeResult<Value> + Send + Sync + 'static,
+	{
+		self.natives.insert(name.into(), Arc::new(function));
+		self
+	}
+
+	/// Compile `source` into a program, which can be shared between runtimes and threads
+	pub fn compile(&self, source: &str) -> EngineResult<Arc<Program>> {
+		let mut compiler = Compiler::new(source);
+		for name in self.natives.keys() {
+			compiler.declare_native(name.clone());
+		}
+		compiler.compile()?;
+		Ok(Arc::new(Program {
+			baggage: compiler.baggage.iter().map(|b| b.unzip()).collect(),
+			scope: compiler.scope,
+			result: compiler.result,
+		}))
+	}
+
+	/// Make a runtime that is ready to run `program`, for hosts that need to configure it first
+	pub fn runtime(&self, program: &Arc<Program>) -> Runtime {
+		let mut runtime = Runtime::from_program(program.clone());
+		runtime.natives = self.natives.clone();
+		runtime
+	}
+
+	/// Run `program` and return its value
+	pub fn run(&self, program: &Arc<Program>) -> EngineResult<Value> {
+		let mut runtime = self.runtime(program);
+		runtime.exec()?;
+		Ok(runtime.value())
+	}
+
+	/// Compile and run `source`, returning its value
+	pub fn eval(&self, source: &str) -> EngineResult<Value> {
+		self.run(&self.compile(source)?)
+	}
+
+	/// Run `program` and keep it loaded, so that its functions can be called with [Engine::call]
+	pub fn load(&mut self, program: &Arc<Program>) -> EngineResult<Value> {
+		let mut runtime = self.runtime(program);
+		runtime.exec()?;
+		let value = runtime.value();
+		self.loaded = Some(Mutex::new(runtime));
+		Ok(value)
+	}
+
+	/// Call the function `name` defined by the loaded program.
+	/// Calls from several threads take turns, as they share the program's variables.
+	pub fn call(&self, name: &str, args: &[Value]) -> EngineResult<Value> {
+		match &self.loaded {
+			Some(runtime) => {
+				let mut runtime = runtime.lock().unwrap_or_else(PoisonError::into_inner);
+				Ok(runtime.call_function(name, args)?)
+			}
+			None => Err(EngineError::NotLoaded),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::error::{compiler::*, runtime::*};
+
+	#[test]
+	fn eval() {
+		let engine = Engine::new();
+		assert_eq!(engine.eval("1 + 2;"), Ok(Value::VNumber(3.)));
+		assert_eq!(engine.eval(r#"fn f(x) { return "{x}!"; } f("hi");"#), Ok(Value::VString("hi!".into())));
+		assert_eq!(engine.eval("let x = 1;"), Ok(Value::VNil));
+		assert_eq!(engine.eval("1; for i in 0..3 { i; }"), Ok(Value::VNil));
+		assert_eq!(engine.eval("2; exit(1);"), Ok(Value::VNil));
+		assert_eq!(
+			engine.eval("x;"),
+			Err(EngineError::Compiler(CompilerError::ScopeError(ScopeError::UndefinedVariable("x".into()))))
+		);
+		assert_eq!(
+			engine.eval("for x in 1 {}"),
+			Err(EngineError::Runtime(RuntimeError::Iteration(IterationError::NotIterable("num"))))
+		);
+	}
+
+	#[test]
+	fn programs() {
+		let mut engine = Engine::new();
+		engine.register_native("double", |args| args[0].clone() * Value::VNumber(2.));
+		let program = engine.compile("let mut x = double(21); x = x + 0.5; x;").unwrap();
+		assert_eq!(engine.run(&program), Ok(Value::VNumber(42.5)));
+		assert_eq!(engine.run(&program), Ok(Value::VNumber(42.5)));
+
+		let mut runtime = engine.runtime(&program);
+		runtime.exec().unwrap();
+		assert_eq!(runtime.value(), Value::VNumber(42.5));
+	}
+
+	#[test]
+	fn callbacks() {
+		let mut engine = Engine::new();
+		assert_eq!(engine.call("on_event", &[]), Err(EngineError::NotLoaded));
+
+		let program = engine.compile(r#"
+			let mut seen = 0;
+			let greeting = "hello";
+			fn on_event(name, times = 1) {
+				seen = seen + times;
+				return "{greeting} {name} #{seen}";
+			}
+			fn fail(x) { return x + 1; }
+			fn quit() { exit(3); }
+		"#).unwrap();
+		assert_eq!(engine.load(&program), Ok(Value::VNil));
+		assert_eq!(
+			engine.call("on_event", &[Value::VString("click".into())]),
+			Ok(Value::VString("hello click #1".into()))
+		);
+		assert!(matches!(engine.call("fail", &[Value::VNil]), Err(EngineError::Runtime(RuntimeError::Arithmetic(_)))));
+		assert_eq!(
+			engine.call("on_event", &[Value::VString("key".into()), Value::VNumber(2.)]),
+			Ok(Value::VString("hello key #3".into()))
+		);
+		assert_eq!(
+			engine.call("missing", &[]),
+			Err(EngineError::Runtime(RuntimeError::Call(CallError::UndefinedFunction("missing".into()))))
+		);
+		assert_eq!(
+			engine.call("greeting", &[]),
+			Err(EngineError::Runtime(RuntimeError::Call(CallError::NotCallable("str"))))
+		);
+		assert_eq!(engine.call("quit", &[]), Ok(Value::VNil));
+		assert_eq!(
+			engine.call("on_event", &[Value::VString("click".into())]),
+			Err(EngineError::Runtime(RuntimeError::Call(CallError::Exited(3))))
+		);
+	}
+
+	#[test]
+	fn shared_between_threads() {
+		let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+		let mut engine = Engine::new();
+		let counter = calls.clone();
+		engine.register_native("double", move |args| {
+			counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+			args[0].clone() * Value::VNumber(2.)
+		});
+		let program = engine.compile("fn f(n) { return double(n) + 1; } f(20);").unwrap();
+		engine.load(&program).unwrap();
+
+		let engine = Arc::new(engine);
+		let threads = (0..8).map(|i| {
+			let (engine, program) = (engine.clone(), program.clone());
+			std::thread::spawn(move || {
+				let mut runtime = engine.runtime(&program);
+				runtime.exec().unwrap();
+				(runtime.value(), engine.eval(&format!("double({});", i)))
+			})
+		}).collect::<Vec<_>>();
+		for (i, thread) in threads.into_iter().enumerate() {
+			assert_eq!(thread.join().unwrap(), (Value::VNumber(41.), Ok(Value::VNumber(i as f32 * 2.))));
+		}
+		assert_eq!(calls.load(std::sync::atomic::Ordering::Relaxed), 17);
+
+		// The loaded program is shared too, so its variables see every thread's calls
+		let program = engine.compile("let mut calls = 0; fn count() { calls = calls + 1; return calls; }").unwrap();
+		let mut engine = Arc::try_unwrap(engine).ok().unwrap();
+		engine.load(&program).unwrap();
+		let engine = Arc::new(engine);
+		let threads = (0..8).map(|_| {
+			let engine = engine.clone();
+			std::thread::spawn(move || {
+				for _ in 0..10 {
+					assert!(matches!(engine.call("count", &[]), Ok(Value::VNumber(_))));
+				}
+			})
+		}).collect::<Vec<_>>();
+		for thread in threads {
+			thread.join().unwrap();
+		}
+		assert_eq!(engine.call("count", &[]), Ok(Value::VNumber(81.)));
+	}
+}
@@ -2,5 +2,7 @@ pub mod bag;
 pub mod compiler;
 pub mod runtime;
 pub mod fileio;
+pub mod engine;
+pub mod value;
 
 pub trait BlushError {}
\ No newline at end of file
@@ -1,12 +1,14 @@
 use std::fmt;
 use super::BlushError;
-use crate::TokenKind;
+use crate::{TokenKind, compiler::Type};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum CompilerError {
 	ExternalError(String, String),
 	TokenError(TokenError),
-	RegisterError(RegisterError)
+	RegisterError(RegisterError),
+	ScopeError(ScopeError),
+	TypeError(TypeError),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -24,6 +26,41 @@ pub enum RegisterError {
 	NoEmptyRegisters
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScopeError {
+	UndefinedVariable(String),
+	/// Assigning to a variable that wasn't declared with `let mut`, or to a constant
+	Immutable(String),
+	/// Constants must be initialized with a literal or another constant
+	NotConstant(String),
+	ReturnOutsideFunction,
+	/// A function with two parameters of the same name
+	DuplicateParameter {
+		function: String,
+		name: String,
+	},
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeError {
+	Mismatch {
+		expected: Type,
+		found: Type,
+		at: Location,
+	},
+	UnknownType {
+		name: String,
+		at: Location,
+	},
+}
+
+/// A position in the source, counting from 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+	pub line: usize,
+	pub column: usize,
+}
+
 impl BlushError for CompilerError {}
 
 impl fmt::Display for CompilerError {
@@ -0,0 +1,37 @@
+use std::fmt;
+use super::{BlushError, compiler::CompilerError, runtime::RuntimeError};
+
+/// An error from any stage of running a script through the [crate::Engine]
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineError {
+    Compiler(CompilerError),
+    Runtime(RuntimeError),
+    /// [crate::Engine::call] was used before a program was loaded
+    NotLoaded,
+}
+
+impl From<CompilerError> for EngineError {
+    fn from(e: CompilerError) -> Self {
+        Self::Compiler(e)
+    }
+}
+
+impl From<RuntimeError> for EngineError {
+    fn from(e: RuntimeError) -> Self {
+        Self::Runtime(e)
+    }
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Compiler(e) => write!(f, "{}", e),
+            Self::Runtime(e) => write!(f, "{}", e),
+            Self::NotLoaded => write!(f, "No program has been loaded"),
+        }
+    }
+}
+
+impl BlushError for EngineError {}
+
+pub type EngineResult<T = ()> = Result<T, EngineError>;
@@ -6,6 +6,18 @@ pub enum RuntimeError {
     ExternalError(String, String),
     Bytecode(BytecodeError),
     Arithmetic(ArithmeticError),
+    Iteration(IterationError),
+    Call(CallError),
+    Capability(CapabilityError),
+    Json(JsonError),
+    Time(TimeError),
+    Regex(RegexError),
+    /// Execution was stopped by one of the runtime's limits, and can be resumed
+    Limit(LimitError),
+    /// The script went over the runtime's memory limits
+    OutOfMemory(MemoryError),
+    /// A snapshot was taken by another version of blush, which is given
+    SnapshotVersion(String),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -13,6 +25,113 @@ pub enum ArithmeticError {
     TypeConflict,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum IterationError {
+    NotIterable(&'static str),
+    /// An iterator object's `next` returned something other than a result map
+    InvalidResult(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallError {
+    NotCallable(&'static str),
+    /// The wrong number of arguments were given. `max` is `None` for functions with a rest parameter.
+    Arity {
+        function: String,
+        min: usize,
+        max: Option<usize>,
+        received: usize,
+    },
+    UnknownArgument {
+        function: String,
+        name: String,
+    },
+    DuplicateArgument {
+        function: String,
+        name: String,
+    },
+    /// A native function was called that the host didn't register with the runtime
+    UndefinedNative(String),
+    /// The host called a function that the script doesn't define
+    UndefinedFunction(String),
+    /// The host called a function after the script had exited with the code
+    Exited(i32),
+    /// A method was called that values of the type don't have
+    NoMethod {
+        ty: &'static str,
+        name: String,
+    },
+    /// A property was read that values of the type don't have
+    NoProperty {
+        ty: &'static str,
+        name: String,
+    },
+    /// A native function was given an argument it can't do anything with
+    InvalidArgument {
+        function: String,
+        reason: &'static str,
+    },
+    /// A native function was given an argument of the wrong type
+    ArgumentType {
+        function: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+}
+
+/// The script tried to do something that the host hasn't allowed
+#[derive(Debug, Clone, PartialEq)]
+pub enum CapabilityError {
+    /// Reading from a path outside of the readable directories
+    Read(String),
+    /// Writing to a path outside of the writable directories
+    Write(String),
+    /// Reading an environment variable that isn't allowed
+    Env(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonError {
+    Parse(String),
+    /// A value of the type can't be written as JSON
+    Unserialisable(&'static str),
+    /// A number in the document can't be stored exactly, i.e. an integer above 2^24
+    Precision(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeError {
+    Parse(String),
+    /// The format string for `time.format` isn't valid
+    Format(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegexError {
+    /// The pattern isn't a valid regular expression. `position` is the character that the problem was
+    /// found at, if there is one.
+    Invalid {
+        pattern: String,
+        position: Option<usize>,
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LimitError {
+    OutOfFuel,
+    DeadlineExceeded,
+    Interrupted,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MemoryError {
+    /// Values took up more than `limit` bytes
+    Heap { limit: usize, used: usize },
+    /// Functions were nested more than `limit` calls deep
+    CallDepth(usize),
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum BytecodeError {
     Malformed(Vec<u8>, usize, &'static str),
@@ -0,0 +1,44 @@
+use std::fmt;
+use super::BlushError;
+
+/// An error converting between Rust data and blush values
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueError {
+    /// A message from serde, i.e. when a value doesn't have the shape the Rust type expects
+    Custom(String),
+    /// A number which can't be stored exactly as a blush number
+    Precision(String),
+    /// Map keys have to be strings (or numbers and chars, which are written as strings)
+    KeyMustBeString,
+    /// Values of the type can't be converted, i.e. functions
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Custom(msg) => write!(f, "{}", msg),
+            Self::Precision(n) => write!(f, "{} can't be stored exactly as a number", n),
+            Self::KeyMustBeString => write!(f, "map keys must be strings"),
+            Self::Unsupported(ty) => write!(f, "values of type {} can't be converted", ty),
+        }
+    }
+}
+
+impl std::error::Error for ValueError {}
+
+impl serde::ser::Error for ValueError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+impl serde::de::Error for ValueError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+impl BlushError for ValueError {}
+
+pub type ValueResult<T = ()> = Result<T, ValueError>;
@@ -2,10 +2,10 @@ use std::slice::Iter;
 
 use crate::{BLUSH_VER, error::fileio::*, OpenedBag, Compiler, runtime::{CompilerScope}};
 
-const BLUSHPROGRAM: &'static str = "BLUSHPROGRAM";
-const PROGSTART: &'static str = "PROGSTART";
-const PROGEND: &'static str = "PROGEND";
-const SCOPESTART: &'static str = "SCOPESTART";
+const BLUSHPROGRAM: &str = "BLUSHPROGRAM";
+const PROGSTART: &str = "PROGSTART";
+const PROGEND: &str = "PROGEND";
+const SCOPESTART: &str = "SCOPESTART";
 
 
 pub fn ser(compiler: &Compiler) -> FileIOResult<Vec<u8>> {
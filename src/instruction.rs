@@ -25,13 +25,20 @@
 	L(x) : Local at index x
 
 	# Boolean Operations (Eq, Lt, etc.)
-    The instruction for the comparison is followed by a move instruction for the false case.
+    Comparisons store their result as a bool, like arithmetic does. `>` and `>=` are LT and LE with their
+    operands swapped.
     Ex:
-    1 Lt 0 1    If R(0) < R(1), IC becomes 5 (current position + 2 + increment in main loop).
-                Else, IC is incremented in the main VM loop
-    2 Move 9    Move to 9 if the expression is false
-    3 Add 0 2 0 Add R(2) to R(0) if the expression is true
-    4 ...       rest of program
+    1 Lt 1 0 2  R(2) = R(1) < R(0), i.e. `R(0) > R(1)`
+
+	# Iteration
+	NEXT is followed by a jump out of the loop, which is skipped while the iterator still has values.
+	Ex:
+	1 Iter 0 1    R(1) = iterator over R(0)
+	2 Next 1 2    If R(1) yields a value, R(2) = value and IC skips the jump
+	3 Jump 0 9    Leave the loop once R(1) is done
+	4 Let  0 2    Bind the value to the loop variable
+	5 ...         loop body
+	6 Jump 0 2    Back to the NEXT
 */
 #[allow(unused)]
 #[repr(u8)]
@@ -42,14 +49,28 @@ pub enum Instruction {
     Sub,   // 2  SUB   A B C  R(C) = R(A) - R(B)
     Mul,   // 3  MUL   A B C  R(C) = R(A) * R(B)
     Div,   // 4  DIV   A B C  R(C) = R(A) / R(B)
-    Eq,    // 5  EQ    A B    if R(A) == R(B) then IC+=2
-    Ne,    // 6  NE    A B    if R(A) != R(B) then IC+=2
-    Lt,    // 7  LT    A B    if R(A) <  R(B) then IC+=2
-    Le,    // 8  LE    A B    if R(A) <= R(B) then IC+=2
+    Eq,    // 5  EQ    A B C  R(C) = R(A) == R(B)
+    Ne,    // 6  NE    A B C  R(C) = R(A) != R(B)
+    Lt,    // 7  LT    A B C  R(C) = R(A) <  R(B)
+    Le,    // 8  LE    A B C  R(C) = R(A) <= R(B)
     Not,   // 9  NOT   A B    R(B) = !R(A)
     Neg,   // 10 NEG   A B    R(B) = -R(A)
     Let,   // 11 LET   L A    Vv(L) = R(A)
     Read,  // 12 READ  I A    R(A) = V(R(I))
     Set,   // 13 SET   I A    V(I) = R(A)
     Move,  // 14 Move  T      IC = T
+    Jump,  // 15 JUMP  B T    Bag = B, IC = T
+    Iter,  // 16 ITER  A B    R(B) = iterator over R(A)
+    Next,  // 17 NEXT  A B    if R(A) yields a value then R(B) = value, IC+=3
+    List,  // 18 LIST  A      R(A) = []
+    Map,   // 19 MAP   A      R(A) = {}
+    Push,  // 20 PUSH  A B    R(A).push(R(B))
+    Insert,// 21 INSERT A K V R(A)[R(K)] = R(V)
+    Range, // 22 RANGE A B C  R(C) = R(A)..R(B)
+    Concat,// 23 CONCAT A B C R(C) = str(R(A)) + str(R(B))
+    Call,  // 24 CALL  F A N S R(S) = R(F)(positional R(A), named R(N))
+    Return,// 25 RETURN A     return R(A) from the current function
+    Missing,//26 MISSING L    if no argument was given for V(L) then IC+=3
+    Invoke,// 27 INVOKE K A N S R(S) = method R(K) of R(A)[0](positional R(A), named R(N))
+    Get,   // 28 GET   A K S  R(S) = property R(K) of R(A)
 }
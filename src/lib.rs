@@ -5,15 +5,19 @@ pub mod scanner;
 pub mod value;
 pub mod runtime;
 pub mod error;
+pub mod engine;
+pub mod program;
 
 pub mod fileio;
 
-pub(crate) const BLUSH_VER: &'static str = "0.0.1-pre_alpha";
+pub(crate) const BLUSH_VER: &str = "0.0.2-pre_alpha";
 
 pub use bag::*;
 pub use compiler::Compiler;
+pub use engine::Engine;
+pub use program::Program;
 pub use instruction::Instruction;
-pub use value::Value;
+pub use value::{from_value, to_value, Value};
 pub use runtime::Runtime;
 // pub use vm::{Environment, VM};
 pub use scanner::TokenKind;
\ No newline at end of file
@@ -0,0 +1,53 @@
+/*
+	# Programs
+	A [Program] is a compiled script: its bags, and the variables the compiler laid out. Programs are
+	never changed once they have been compiled, so one program can be shared behind an [std::sync::Arc] by any
+	number of runtimes, on any number of threads, without copying its bytecode:
+	```
+	let program = blush::Engine::new().compile("let x = 1;").unwrap();
+	let threads = (0..4).map(|_| {
+		let program = program.clone();
+		std::thread::spawn(move || blush::Runtime::from_program(program).exec())
+	});
+	for thread in threads.collect::<Vec<_>>() {
+		assert_eq!(thread.join().unwrap(), Ok(()));
+	}
+	```
+*/
+
+use serde::{Deserialize, Serialize};
+
+use crate::{OpenedBag, runtime::CompilerScope};
+
+/// A compiled script, which can be run any number of times
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Program {
+	pub baggage: Vec<OpenedBag>,
+	pub scope: CompilerScope,
+	/// The register that holds the program's value once it has finished
+	pub result: Option<u8>,
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use crate::{Engine, Runtime};
+
+	#[test]
+	fn shared_between_threads() {
+		let program = Engine::new().compile("fn square(n) { return n * n; } let x = square(12);").unwrap();
+		let threads = (0..8).map(|_| {
+			let program = program.clone();
+			std::thread::spawn(move || {
+				let mut runtime = Runtime::from_program(program);
+				runtime.exec().unwrap();
+				runtime.scope.vars.iter().find(|v| v.name == "x").unwrap().value.to_string()
+			})
+		}).collect::<Vec<_>>();
+		for thread in threads {
+			assert_eq!(thread.join().unwrap(), "144");
+		}
+		assert_eq!(Arc::strong_count(&program), 1);
+		assert!(Arc::ptr_eq(&Runtime::from_program(program.clone()).program, &program));
+	}
+}
@@ -6,21 +6,99 @@
     ## Bytecode Execution
 	Bytecode instructions are matched to a function which consumes later bytes as arguments.
 	Ex. Binary arithmetic instructions have 3 arguments in the 3 following bytes
+	Once the end of a bag is reached, execution continues at the start of the next bag.
 */
 
-use crate::{Value, OpenedBag, error::runtime::*};
+use std::{collections::{BTreeMap, HashMap}, io::Write, sync::{atomic::{AtomicBool, Ordering}, Arc}, time::Instant};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{Value, OpenedBag, Program, error::runtime::*};
 
 mod environment;
+mod function;
+mod host;
+mod hooks;
+mod iter;
+mod native;
+mod snapshot;
+pub mod stdlib;
 pub use environment::*;
+pub use function::*;
+pub use host::*;
+pub use hooks::*;
+pub use iter::*;
+pub use native::*;
 
 
 pub struct Runtime {
 	pub scope: RuntimeScope,
     pub ic: usize,
-	pub compiler_scope: CompilerScope,
     pub registers: Vec<Value>,
-	pub baggage: Vec<OpenedBag>,
+	/// The program being run, which may be shared with other runtimes
+	pub program: Arc<Program>,
 	pub current_bag: usize,
+	pub frames: Vec<Frame>,
+	/// Calls made by the host that haven't returned yet, innermost last
+	pub host_calls: Vec<HostCall>,
+	/// Native functions registered by the host, by name
+	pub natives: HashMap<String, NativeFn>,
+	/// Where `print` and `println` write to, stdout by default
+	pub output: Box<dyn Write + Send>,
+	/// The directories that the `fs` module may access
+	pub fs: stdlib::FsCapabilities,
+	/// Where the `time` module gets the time from
+	pub clock: Box<dyn stdlib::Clock>,
+	/// The generator behind the `random` module
+	pub rng: ChaCha8Rng,
+	/// The arguments the script was run with, for `env.args`
+	pub args: Vec<String>,
+	/// The environment variables that `env.get` may read
+	pub env: stdlib::EnvCapabilities,
+	/// The code the script exited with, if it called `exit`
+	pub exit_code: Option<i32>,
+	/// How many more instructions may be run, or `None` for no limit
+	pub fuel: Option<u64>,
+	/// When execution has to stop by
+	pub deadline: Option<Instant>,
+	/// Set from any thread to stop execution
+	pub interrupt: Arc<AtomicBool>,
+	/// The most bytes that values may take up, or `None` for no limit
+	pub max_heap: Option<usize>,
+	/// How many function calls deep scripts may go, or `None` for no limit
+	pub max_depth: Option<usize>,
+	/// The size of the heap when it was last measured, and how much has been allocated since
+	measured: usize,
+	allocated: usize,
+	/// Told about what the script does as it runs
+	pub observer: Option<Box<dyn Observer>>,
+}
+
+/// Read the result map returned by an iterator object's `next`, giving `None` once it is done, otherwise
+/// the item and the object's new state, if there is one
+fn iterator_result(result: Value) -> RuntimeResult<Option<(Value, Option<Value>)>> {
+	let mut result = match result {
+		Value::VMap(map) => map,
+		v => return Err(RuntimeError::Iteration(IterationError::InvalidResult(v.type_name()))),
+	};
+	if result.get("done") == Some(&Value::VBool(true)) {
+		return Ok(None);
+	}
+	Ok(Some((result.remove("value").unwrap_or(Value::VNil), result.remove("state"))))
+}
+
+/// How far a script got after [Runtime::step] or [Runtime::run_for]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+	/// An instruction was run, and there are more to run
+	Running,
+	/// The program has finished, with its value
+	Finished(Value),
+	/// The instructions it was given have been used up before the program finished
+	Yielded,
+	/// The program stopped with an error
+	Error(RuntimeError),
 }
 
 macro_rules! operation {
@@ -34,60 +112,251 @@ macro_rules! operation {
 	($self:ident.$op:tt, C) => {{ // Comparison
 		let lhs = $self.at_next()?;
 		let rhs = $self.at_next()?;
-		if lhs $op rhs {
-			$self.ic+=2;
-		}
+		$self.set_next(Value::VBool(lhs $op rhs))?;
 		Ok(())
 	}};
 
 	($self:ident.$op:tt, U) => {{ // Unary
-		let idx = $self.next()?;
-		let value = $self.registers[idx as usize].clone();
-		$self.set(idx, ($op value)?);
+		let value = $self.at_next()?;
+		$self.set_next(($op value)?)?;
 		Ok(())
 	}}
 }
 
 impl Runtime {
     pub fn new(baggage: Vec<OpenedBag>, scope: Option<RuntimeScope>, compiler_scope: CompilerScope) -> Self {
+		let mut runtime = Self::from_program(Arc::new(Program { baggage, scope: compiler_scope, result: None }));
+		if let Some(scope) = scope {
+			runtime.scope = scope;
+		}
+		runtime
+	}
+
+	/// Make a runtime for `program`, which is shared rather than copied
+	pub fn from_program(program: Arc<Program>) -> Self {
         Self {
-			scope: scope.unwrap_or(compiler_scope.clone().into()),
-			compiler_scope,
+			scope: program.scope.clone().into(),
             ic: 0,
             registers: vec![Value::VBool(false); u8::MAX.into()],
-			baggage,
+			program,
 			current_bag: 0,
+			frames: vec![],
+			host_calls: vec![],
+			natives: HashMap::new(),
+			output: Box::new(std::io::stdout()),
+			fs: Default::default(),
+			clock: Box::<stdlib::SystemClock>::default(),
+			rng: ChaCha8Rng::from_entropy(),
+			args: vec![],
+			env: Default::default(),
+			exit_code: None,
+			fuel: None,
+			deadline: None,
+			interrupt: Arc::new(AtomicBool::new(false)),
+			max_heap: None,
+			max_depth: None,
+			measured: 0,
+			allocated: 0,
+			observer: None,
         }
     }
 
+	/// Send the output of `print` and `println` to `output` instead of stdout
+	pub fn set_output(&mut self, output: impl Write + Send + 'static) {
+		self.output = Box::new(output);
+	}
+
+	/// Use `clock` as the time source for the `time` module instead of the system's clock
+	pub fn set_clock(&mut self, clock: impl stdlib::Clock + 'static) {
+		self.clock = Box::new(clock);
+	}
+
+	/// Seed the `random` module's generator, so that scripts get the same numbers on every run
+	pub fn seed(&mut self, seed: u64) {
+		self.rng = ChaCha8Rng::seed_from_u64(seed);
+	}
+
+	/// Register a native function which scripts can call as `name`.
+	/// The compiler must also be told about the name with [crate::Compiler::declare_native].
+	pub fn register_native<F>(&mut self, name: impl Into<String>, function: F)
+	where
+		F: Fn(&[Value]) -> RuntimeResult<Value> + Send + Sync + 'static,
+	{
+		self.natives.insert(name.into(), Arc::new(function));
+	}
+
     pub fn exec(&mut self) -> RuntimeResult {
-        loop {
-            let current: u8 = self.current();
-            match current {
-				0  /*Const*/ => {self.constant()?;}
-				1  /*Add*/   => {self.add()?;}
-				2  /*Sub*/   => {self.sub()?;}
-				3  /*Mul*/   => {self.mul()?;}
-				4  /*Div*/   => {self.div()?;}
-				5  /*Eq*/    => {self.eq()?;} 
-				6  /*Ne*/    => {self.ne()?;}
-				7  /*Lt*/    => {self.lt()?;}
-				8  /*Le*/    => {self.le()?;}
-				9  /*Not*/   => {self.not()?;}
-				10 /*Neg*/   => {self.neg()?;}
-				11 /*Let*/   => {self.let_declr()?;}
-				12 /*Read*/  => {}
-				13 /*Set*/   => {}
-				14 /*Move*/  => {self.ic = self.next()? as usize;}
-				_ => return malformed_bytecode!(self.bytecode(), self.ic, "Unexpected byte")
+		while !self.finished() {
+			if let Err(error) = self.instruction() {
+				self.observe(|observer, state| observer.error(state, &error));
+				return Err(error);
 			}
-            self.ic += 1;
-            if self.ic == self.bytecode().len() {
-                break;
-            }
-        }
-        Ok(())
-    }
+		}
+		Ok(())
+	}
+
+	/// Run one instruction, returning whether the program has finished
+	pub fn step(&mut self) -> Status {
+		match self.run_for(1) {
+			Status::Yielded => Status::Running,
+			status => status,
+		}
+	}
+
+	/// Run at most `n` instructions, yielding if the program hasn't finished by then
+	pub fn run_for(&mut self, n: usize) -> Status {
+		for _ in 0..n {
+			if self.finished() {
+				break;
+			}
+			if let Err(error) = self.instruction() {
+				self.observe(|observer, state| observer.error(state, &error));
+				return Status::Error(error);
+			}
+		}
+		match self.finished() {
+			true => Status::Finished(self.value()),
+			false => Status::Yielded,
+		}
+	}
+
+	/// Whether there are no instructions left to run, moving on to the next bag at the end of each one
+	fn finished(&mut self) -> bool {
+		while self.current_bag < self.program.baggage.len() && self.ic >= self.bytecode().len() {
+			self.current_bag += 1;
+			self.ic = 0;
+		}
+		self.current_bag >= self.program.baggage.len()
+	}
+
+	/// The value of the program once it has finished, i.e. the value of its final statement
+	pub fn value(&self) -> Value {
+		match self.program.result {
+			// A script that called `exit` didn't get to its final statement
+			Some(register) if self.exit_code.is_none() => self.registers[register as usize].clone(),
+			_ => Value::VNil,
+		}
+	}
+
+	fn instruction(&mut self) -> RuntimeResult {
+		self.check_limits()?;
+		let current: u8 = self.current();
+		self.observe(|observer, state| observer.instruction(state, current));
+		match current {
+			0  /*Const*/ => {self.constant()?;}
+			1  /*Add*/   => {self.add()?;}
+			2  /*Sub*/   => {self.sub()?;}
+			3  /*Mul*/   => {self.mul()?;}
+			4  /*Div*/   => {self.div()?;}
+			5  /*Eq*/    => {self.eq()?;} 
+			6  /*Ne*/    => {self.ne()?;}
+			7  /*Lt*/    => {self.lt()?;}
+			8  /*Le*/    => {self.le()?;}
+			9  /*Not*/   => {self.not()?;}
+			10 /*Neg*/   => {self.neg()?;}
+			11 /*Let*/   => {self.let_declr()?;}
+			12 /*Read*/  => {self.read()?;}
+			13 /*Set*/   => {self.set_var()?;}
+			14 /*Move*/  => {self.ic = self.next()? as usize;}
+			15 /*Jump*/  => {self.jump()?; return Ok(());}
+			16 /*Iter*/  => {self.iter()?;}
+			17 /*Next*/  => {if self.next_item()? {return Ok(());}}
+			18 /*List*/  => {self.set_next(Value::VList(vec![]))?;}
+			19 /*Map*/   => {self.set_next(Value::VMap(Default::default()))?;}
+			20 /*Push*/  => {self.push()?;}
+			21 /*Insert*/=> {self.insert()?;}
+			22 /*Range*/ => {self.range()?;}
+			23 /*Concat*/=> {self.concat()?;}
+			24 /*Call*/  => {if self.call()? {return Ok(());}}
+			25 /*Return*/=> {self.ret()?;}
+			26 /*Missing*/=>{self.missing()?;}
+			27 /*Invoke*/=> {self.invoke()?;}
+			28 /*Get*/   => {self.get()?;}
+			_ => return malformed_bytecode!(self.bytecode(), self.ic, "Unexpected byte")
+		}
+		self.reserve(0)?;
+		self.ic += 1;
+		Ok(())
+	}
+
+	/// Tell the observer about something, if there is one
+	fn observe(&mut self, hook: impl FnOnce(&mut dyn Observer, &State)) {
+		if let Some(mut observer) = self.observer.take() {
+			let state = State {
+				bag: self.current_bag,
+				ic: self.ic,
+				registers: &self.registers,
+				depth: self.frames.len(),
+			};
+			hook(observer.as_mut(), &state);
+			self.observer = Some(observer);
+		}
+	}
+
+	/// Watch the script with `observer`
+	pub fn set_observer(&mut self, observer: impl Observer + 'static) {
+		self.observer = Some(Box::new(observer));
+	}
+
+	/// A handle which stops execution when it is set to true, from any thread
+	pub fn interrupt_handle(&self) -> Arc<AtomicBool> {
+		self.interrupt.clone()
+	}
+
+	/// Check that the next instruction may run, using up one unit of fuel.
+	/// Limits are checked before anything is changed, so execution can be resumed by calling [Runtime::exec]
+	/// again once the limit has been lifted.
+	fn check_limits(&mut self) -> RuntimeResult {
+		if self.interrupt.swap(false, Ordering::Relaxed) {
+			return Err(RuntimeError::Limit(LimitError::Interrupted));
+		}
+		if self.deadline.map(|d| Instant::now() >= d).unwrap_or(false) {
+			return Err(RuntimeError::Limit(LimitError::DeadlineExceeded));
+		}
+		match &mut self.fuel {
+			Some(0) => Err(RuntimeError::Limit(LimitError::OutOfFuel)),
+			Some(fuel) => {
+				*fuel -= 1;
+				Ok(())
+			}
+			None => Ok(()),
+		}
+	}
+
+	/// Check that `bytes` more can be allocated without going over [Runtime::max_heap].
+	/// Allocations are tallied as values are stored, and the heap is only measured once the tally
+	/// could be over the limit, as most of what was allocated has usually been dropped again by then.
+	/// Natives which build large values have to reserve the space before they build them, as the tally
+	/// is only checked once an instruction has finished.
+	pub fn reserve(&mut self, bytes: usize) -> RuntimeResult {
+		let limit = match self.max_heap {
+			Some(limit) => limit,
+			None => return Ok(()),
+		};
+		if self.measured + self.allocated + bytes <= limit {
+			return Ok(());
+		}
+		self.measured = self.heap_size();
+		self.allocated = 0;
+		if self.measured + bytes > limit {
+			return Err(RuntimeError::OutOfMemory(MemoryError::Heap { limit, used: self.measured + bytes }));
+		}
+		Ok(())
+	}
+
+	/// Roughly how many bytes the values held by the runtime take up
+	pub fn heap_size(&self) -> usize {
+		let values = self.registers.iter()
+			.chain(self.scope.vars.iter().map(|v| &v.value))
+			.chain(self.frames.iter().flat_map(|f| f.registers.iter().chain(f.saved.iter())));
+		values.map(Value::heap_size).sum()
+	}
+
+	fn track(&mut self, value: &Value) {
+		if self.max_heap.is_some() {
+			self.allocated += value.heap_size();
+		}
+	}
 
     fn next(&mut self) -> RuntimeResult<u8> {
         self.ic += 1;
@@ -99,7 +368,7 @@ impl Runtime {
     }
 
 	fn current_block(&self) -> &OpenedBag {
-		&self.baggage[self.current_bag]
+		&self.program.baggage[self.current_bag]
 	}
 	fn bytecode(&self) -> &Vec<u8> {
 		&self.current_block().bytecode
@@ -119,6 +388,7 @@ impl Runtime {
     }
 
     fn set(&mut self, idx: u8, value: Value) {
+		self.track(&value);
         self.registers[idx as usize] = value;
     }
 
@@ -129,13 +399,13 @@ impl Runtime {
     }
 
     pub fn constant(&mut self) -> RuntimeResult {
-		dbg!(self.current());
-        let idx = self.next()?;
-        let len = self.next()?;
-        let data = self.constants()[(idx as usize)..(idx + len) as usize].to_vec();
-		dbg!(&data);
-		dbg!(idx, len);
-		let value: Value = bincode::deserialize(&data).unwrap();
+        let idx = self.next()? as usize;
+        let len = self.next()? as usize;
+        let data = self.constants()[idx..idx + len].to_vec();
+		let value: Value = match bincode::deserialize(&data) {
+			Ok(value) => value,
+			Err(_) => return malformed_bytecode!(self.bytecode(), self.ic, "Constant can't be decoded"),
+		};
         self.set_next(value)?;
         Ok(())
     }
@@ -183,7 +453,442 @@ impl Runtime {
 	pub fn let_declr(&mut self) -> RuntimeResult { // 11 LET   L A    Vv(L) = R(A)
 		let local_idx = self.next()?;
 		let v = self.at_next()?;
-		self.scope.vars[local_idx as usize].value = v;
+		self.write(local_idx, v);
+		Ok(())
+	}
+
+	fn write(&mut self, slot: u8, value: Value) {
+		let var = &mut self.scope.vars[slot as usize];
+		var.value = value;
+		if self.observer.is_some() {
+			let (name, value) = (var.name.clone(), var.value.clone());
+			self.observe(|observer, state| observer.write(state, slot, &name, &value));
+		}
+	}
+
+	pub fn read(&mut self) -> RuntimeResult { // 12 READ  I A    R(A) = V(I)
+		let local_idx = self.next()?;
+		let v = self.scope.vars[local_idx as usize].value.clone();
+		self.set_next(v)
+	}
+
+	pub fn set_var(&mut self) -> RuntimeResult { // 13 SET   I A    V(I) = R(A)
+		let local_idx = self.next()?;
+		let v = self.at_next()?;
+		self.write(local_idx, v);
+		Ok(())
+	}
+
+	pub fn jump(&mut self) -> RuntimeResult { // 15 JUMP  B T    Bag = B, IC = T
+		let bag = self.next()? as usize;
+		let target = self.next()? as usize;
+		if bag >= self.program.baggage.len() {
+			return malformed_bytecode!(self.bytecode(), self.ic, "Jump to a bag that doesn't exist");
+		}
+		self.current_bag = bag;
+		self.ic = target;
+		Ok(())
+	}
+
+	pub fn iter(&mut self) -> RuntimeResult { // 16 ITER  A B    R(B) = iterator over R(A)
+		let iterable = self.at_next()?;
+		let iter = Iter::try_from(iterable)?;
+		self.set_next(Value::VIter(Box::new(iter)))
+	}
+
+	/// Returns whether execution jumped into an iterator's `next` function, which RETURN finishes
+	pub fn next_item(&mut self) -> RuntimeResult<bool> { // 17 NEXT  A B    if R(A) yields a value then R(B) = value, IC+=3
+		let idx = self.next()?;
+		let store = self.next()?;
+		let item = match &mut self.registers[idx as usize] {
+			Value::VIter(iter) => match iter.as_mut() {
+				Iter::Object(object) => {
+					let object = object.clone();
+					return match self.next_from(object, idx, store)? {
+						Some(result) => self.advanced(idx, store, result).map(|_| false),
+						None => Ok(true),
+					};
+				}
+				iter => iter.next(),
+			},
+			v => return Err(RuntimeError::Iteration(IterationError::NotIterable(v.type_name()))),
+		};
+		self.yielded(store, item);
+		Ok(false)
+	}
+
+	/// Call the `next` member of the iterator object in R(`iterator`), returning its result map. Script
+	/// functions are entered rather than run to completion, so there's no result yet.
+	fn next_from(&mut self, object: Value, iterator: u8, store: u8) -> RuntimeResult<Option<Value>> {
+		let next = match &object {
+			Value::VHost(host) => return host.0.call("next", &[]).map(Some),
+			Value::VMap(map) => map.get("next").cloned().unwrap_or(Value::VNil),
+			v => return Err(RuntimeError::Iteration(IterationError::NotIterable(v.type_name()))),
+		};
+		match next {
+			Value::VNative(name) => self.call_native(&name, vec![object], BTreeMap::new()).map(Some),
+			Value::VFunction(function) => {
+				let bound = function.bind(vec![object], BTreeMap::new())?;
+				self.enter(&function, bound, store, (self.current_bag, self.ic))?;
+				if let Some(frame) = self.frames.last_mut() {
+					frame.iterating = Some(iterator);
+				}
+				Ok(None)
+			}
+			v => Err(RuntimeError::Call(CallError::NotCallable(v.type_name()))),
+		}
+	}
+
+	/// Finish a NEXT with the result map of the `next` of the iterator object in R(`iterator`)
+	fn advanced(&mut self, iterator: u8, store: u8, result: Value) -> RuntimeResult {
+		let item = match iterator_result(result)? {
+			Some((item, state)) => {
+				if let (Some(state), Value::VIter(iter)) = (state, &mut self.registers[iterator as usize]) {
+					if let Iter::Object(Value::VMap(object)) = iter.as_mut() {
+						object.insert("state".into(), state);
+					}
+				}
+				Some(item)
+			}
+			None => None,
+		};
+		self.yielded(store, item);
+		Ok(())
+	}
+
+	/// Finish a NEXT, storing the item and skipping over the jump out of the loop if there is one
+	fn yielded(&mut self, store: u8, item: Option<Value>) {
+		if let Some(item) = item {
+			self.set(store, item);
+			self.ic += 3;
+		}
+	}
+
+	pub fn push(&mut self) -> RuntimeResult { // 20 PUSH  A B    R(A).push(R(B))
+		let idx = self.next()? as usize;
+		let item = self.at_next()?;
+		self.track(&item);
+		match &mut self.registers[idx] {
+			Value::VList(items) => items.push(item),
+			_ => return Err(RuntimeError::Arithmetic(ArithmeticError::TypeConflict)),
+		}
+		Ok(())
+	}
+
+	pub fn insert(&mut self) -> RuntimeResult { // 21 INSERT A K V R(A)[R(K)] = R(V)
+		let idx = self.next()? as usize;
+		let key = match self.at_next()? {
+			Value::VString(key) => key,
+			_ => return Err(RuntimeError::Arithmetic(ArithmeticError::TypeConflict)),
+		};
+		let value = self.at_next()?;
+		self.track(&value);
+		match &mut self.registers[idx] {
+			Value::VMap(map) => {map.insert(key, value);}
+			_ => return Err(RuntimeError::Arithmetic(ArithmeticError::TypeConflict)),
+		}
+		Ok(())
+	}
+
+	pub fn range(&mut self) -> RuntimeResult { // 22 RANGE A B C  R(C) = R(A)..R(B)
+		let start = self.at_next()?;
+		let end = self.at_next()?;
+		match (start, end) {
+			(Value::VNumber(start), Value::VNumber(end)) => self.set_next(Value::VRange(start, end)),
+			_ => Err(RuntimeError::Arithmetic(ArithmeticError::TypeConflict)),
+		}
+	}
+
+	pub fn concat(&mut self) -> RuntimeResult { // 23 CONCAT A B C R(C) = str(R(A)) + str(R(B))
+		let lhs = self.at_next()?;
+		let rhs = self.at_next()?;
+		// Exact for strings, and roughly the size of anything else as text
+		self.reserve(lhs.heap_size() + rhs.heap_size())?;
+		self.set_next(Value::VString(format!("{}{}", lhs, rhs)))
+	}
+
+	/// Returns whether execution jumped into a script function, as native calls return straight away
+	pub fn call(&mut self) -> RuntimeResult<bool> { // 24 CALL  F A N S R(S) = R(F)(positional R(A), named R(N))
+		let callee = self.at_next()?;
+		let positional = match self.at_next()? {
+			Value::VList(args) => args,
+			_ => return malformed_bytecode!(self.bytecode(), self.ic, "Arguments must be a list"),
+		};
+		let named = match self.at_next()? {
+			Value::VMap(args) => args,
+			_ => return malformed_bytecode!(self.bytecode(), self.ic, "Named arguments must be a map"),
+		};
+		let store = self.next()?;
+
+		let function = match callee {
+			Value::VFunction(f) => f,
+			Value::VNative(name) => {
+				let value = self.call_native(&name, positional, named)?;
+				self.set(store, value);
+				return Ok(false);
+			}
+			v => return Err(RuntimeError::Call(CallError::NotCallable(v.type_name()))),
+		};
+		let bound = function.bind(positional, named)?;
+		self.enter(&function, bound, store, (self.current_bag, self.ic))?;
+		Ok(true)
+	}
+
+	/// Push a frame for `function` and jump to its start, with its parameters bound to their arguments.
+	/// Once it returns, its value is stored in the caller's R(`store`) and execution continues after `return_to`.
+	fn enter(&mut self, function: &Function, bound: Vec<(u8, Value)>, store: u8, return_to: (usize, usize)) -> RuntimeResult {
+		if let Some(limit) = self.max_depth {
+			if self.frames.len() >= limit {
+				return Err(RuntimeError::OutOfMemory(MemoryError::CallDepth(limit)));
+			}
+		}
+
+		let (start, end) = (function.slots.0 as usize, function.slots.1 as usize);
+		let saved = self.scope.vars[start..end].iter().map(|v| v.value.clone()).collect();
+		let registers = std::mem::replace(&mut self.registers, vec![Value::default(); u8::MAX.into()]);
+		self.frames.push(Frame {
+			function: function.name.clone(),
+			return_to,
+			store,
+			registers,
+			slots: function.slots,
+			saved,
+			given: bound.iter().map(|b| b.0).collect(),
+			iterating: None,
+		});
+
+		self.current_bag = function.entry.0 as usize;
+		self.ic = function.entry.1 as usize;
+		self.observe(|observer, state| observer.call(state, &function.name));
+		for (slot, value) in bound {
+			self.write(slot, value);
+		}
+		Ok(())
+	}
+
+	/// Put back the caller's registers, and the values that the function's slots held before the call
+	fn leave(&mut self, frame: &mut Frame) {
+		self.registers = std::mem::take(&mut frame.registers);
+		for (i, value) in std::mem::take(&mut frame.saved).into_iter().enumerate() {
+			self.write(frame.slots.0 + i as u8, value);
+		}
+	}
+
+	/// Call the top level function `name` from the host, and return its value.
+	/// Variables keep their values between calls, so scripts can keep state for the host.
+	/// If the call runs into a [LimitError], it is left where it stopped and can be finished with
+	/// [Runtime::resume], even after other calls have been made. Any other error unwinds the stack, so
+	/// that the runtime can still be called again. Once the script has called `exit`, it can't be called.
+	pub fn call_function(&mut self, name: &str, args: &[Value]) -> RuntimeResult<Value> {
+		if let Some(code) = self.exit_code {
+			return Err(RuntimeError::Call(CallError::Exited(code)));
+		}
+		let callee = match self.scope.vars.iter().rev().find(|v| v.depth == 0 && v.name == name) {
+			Some(var) => var.value.clone(),
+			None => return Err(RuntimeError::Call(CallError::UndefinedFunction(name.into()))),
+		};
+		let function = match callee {
+			Value::VFunction(f) => f,
+			Value::VNative(native) => return self.call_native(&native, args.to_vec(), BTreeMap::new()),
+			v => return Err(RuntimeError::Call(CallError::NotCallable(v.type_name()))),
+		};
+		let bound = function.bind(args.to_vec(), BTreeMap::new())?;
+
+		// The function returns to just past the end of the baggage, which stops execution
+		let call = HostCall {
+			depth: self.frames.len(),
+			position: (self.current_bag, self.ic),
+			kept: self.registers[0].clone(),
+		};
+		self.enter(&function, bound, 0, (self.program.baggage.len(), 0))?;
+		self.host_calls.push(call);
+		self.finish_call()
+	}
+
+	/// Carry on after a [LimitError], returning the value of the host call that was stopped or, if it was
+	/// the script itself that was stopped, the value of the program
+	pub fn resume(&mut self) -> RuntimeResult<Value> {
+		match self.host_calls.is_empty() {
+			true => {
+				self.exec()?;
+				Ok(self.value())
+			}
+			false => self.finish_call(),
+		}
+	}
+
+	/// Run the innermost host call until it returns, and go back to where execution was before it
+	fn finish_call(&mut self) -> RuntimeResult<Value> {
+		let result = self.exec();
+		let call = match self.host_calls.pop() {
+			Some(call) => call,
+			None => return result.map(|_| Value::VNil),
+		};
+		match result {
+			// The frames are kept, so that the call can be resumed
+			Err(RuntimeError::Limit(e)) => {
+				self.host_calls.push(call);
+				Err(RuntimeError::Limit(e))
+			}
+			Err(e) => {
+				(self.current_bag, self.ic) = call.position;
+				while self.frames.len() > call.depth {
+					let mut frame = self.frames.pop().unwrap();
+					self.leave(&mut frame);
+				}
+				Err(e)
+			}
+			// `exit` ends the script, so there's nothing to go back to
+			Ok(()) if self.exit_code.is_some() => Ok(Value::VNil),
+			Ok(()) => {
+				(self.current_bag, self.ic) = call.position;
+				Ok(std::mem::replace(&mut self.registers[0], call.kept))
+			}
+		}
+	}
+
+	fn call_native(&mut self, name: &str, positional: Vec<Value>, named: BTreeMap<String, Value>) -> RuntimeResult<Value> {
+		if let Some(arg) = named.into_keys().next() {
+			return Err(RuntimeError::Call(CallError::UnknownArgument {
+				function: name.into(),
+				name: arg,
+			}));
+		}
+		match self.natives.get(name) {
+			Some(native) => native(&positional),
+			None => self.builtin(name, &positional),
+		}
+	}
+
+	/// Stop execution once the current instruction is done
+	pub fn halt(&mut self) {
+		self.current_bag = self.program.baggage.len();
+		self.frames.clear();
+		self.host_calls.clear();
+	}
+
+	fn builtin(&mut self, name: &str, args: &[Value]) -> RuntimeResult<Value> {
+		match name {
+			"print" | "println" => {
+				let line = args.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(" ");
+				let end = if name == "println" { "\n" } else { "" };
+				write!(self.output, "{}{}", line, end)
+					.and_then(|_| self.output.flush())
+					.map_err(|e| RuntimeError::ExternalError("io::Error".into(), e.to_string()))?;
+				Ok(Value::VNil)
+			}
+			"exit" => {
+				stdlib::arity("exit", args, 0, Some(1))?;
+				let code = match args.first() {
+					Some(code) => i32::try_from(stdlib::index("exit", code)?).map_err(|_| {
+						RuntimeError::Call(CallError::InvalidArgument {
+							function: "exit".into(),
+							reason: "the exit code is too large",
+						})
+					})?,
+					None => 0,
+				};
+				self.exit_code = Some(code);
+				self.halt();
+				Ok(Value::VNil)
+			}
+			"iterator" => {
+				stdlib::arity("iterator", args, 1, Some(1))?;
+				match &args[0] {
+					Value::VMap(map) if matches!(map.get("next"), Some(Value::VFunction(_) | Value::VNative(_))) => {
+						Ok(Value::VIter(Box::new(Iter::Object(args[0].clone()))))
+					}
+					Value::VHost(_) => Ok(Value::VIter(Box::new(Iter::Object(args[0].clone())))),
+					_ => Err(RuntimeError::Call(CallError::InvalidArgument {
+						function: "iterator".into(),
+						reason: "the object has no next function",
+					})),
+				}
+			}
+			_ => match stdlib::function(name) {
+				Some(function) => function(self, args),
+				None => Err(RuntimeError::Call(CallError::UndefinedNative(name.into()))),
+			},
+		}
+	}
+
+	pub fn ret(&mut self) -> RuntimeResult { // 25 RETURN A     return R(A) from the current function
+		let value = self.at_next()?;
+		let mut frame = match self.frames.pop() {
+			Some(frame) => frame,
+			None => return malformed_bytecode!(self.bytecode(), self.ic, "Return outside of a function"),
+		};
+
+		(self.current_bag, self.ic) = frame.return_to;
+		self.leave(&mut frame);
+		self.observe(|observer, state| observer.ret(state, &frame.function, &value));
+		match frame.iterating {
+			Some(iterator) => self.advanced(iterator, frame.store, value)?,
+			None => self.set(frame.store, value),
+		}
+		Ok(())
+	}
+
+	pub fn invoke(&mut self) -> RuntimeResult { // 27 INVOKE K A N S R(S) = method R(K) of R(A)[0](positional R(A), named R(N))
+		let method = match self.at_next()? {
+			Value::VString(method) => method,
+			_ => return malformed_bytecode!(self.bytecode(), self.ic, "Method name must be a string"),
+		};
+		let positional = match self.at_next()? {
+			Value::VList(args) if !args.is_empty() => args,
+			_ => return malformed_bytecode!(self.bytecode(), self.ic, "Arguments must be a list starting with the receiver"),
+		};
+		let named = match self.at_next()? {
+			Value::VMap(args) => args,
+			_ => return malformed_bytecode!(self.bytecode(), self.ic, "Named arguments must be a map"),
+		};
+		let store = self.next()?;
+
+		if let Value::VHost(host) = &positional[0] {
+			if let Some(arg) = named.into_keys().next() {
+				return Err(RuntimeError::Call(CallError::UnknownArgument { function: method, name: arg }));
+			}
+			let value = host.0.call(&method, &positional[1..])?;
+			self.set(store, value);
+			return Ok(());
+		}
+
+		let ty = positional[0].type_name();
+		let name = format!("{}.{}", ty, method);
+		if !self.natives.contains_key(&name) && stdlib::function(&name).is_none() {
+			return Err(RuntimeError::Call(CallError::NoMethod { ty, name: method }));
+		}
+		let value = self.call_native(&name, positional, named)?;
+		self.set(store, value);
+		Ok(())
+	}
+
+	pub fn get(&mut self) -> RuntimeResult { // 28 GET   A K S  R(S) = property R(K) of R(A)
+		let object = self.at_next()?;
+		let name = match self.at_next()? {
+			Value::VString(name) => name,
+			_ => return malformed_bytecode!(self.bytecode(), self.ic, "Property name must be a string"),
+		};
+		let value = match &object {
+			Value::VMap(map) => Some(map.get(&name).cloned().unwrap_or(Value::VNil)),
+			Value::VHost(host) => host.0.property(&name),
+			_ => None,
+		};
+		match value {
+			Some(value) => self.set_next(value),
+			None => Err(RuntimeError::Call(CallError::NoProperty {
+				ty: object.type_name(),
+				name,
+			})),
+		}
+	}
+
+	pub fn missing(&mut self) -> RuntimeResult { // 26 MISSING L    if no argument was given for V(L) then IC+=3
+		let slot = self.next()?;
+		let given = self.frames.last().map(|f| f.given.contains(&slot)).unwrap_or(false);
+		if !given {
+			self.ic += 3;
+		}
 		Ok(())
 	}
 }
@@ -191,7 +896,7 @@ impl Runtime {
 #[cfg(test)]
 pub(crate) mod tests {
     use super::*;
-	use crate::{runtime::Variable, Bag};
+	use crate::{runtime::Variable, Bag, Compiler};
     pub(crate) mod util {
         use super::*;
 
@@ -204,12 +909,55 @@ pub(crate) mod tests {
             let mut runtime = Runtime::new(
 				baggage,
 				None, 
-				scope.unwrap_or(CompilerScope::default())
+				scope.unwrap_or_default()
 			);
             runtime.exec().unwrap();
             runtime
         }
 
+		/// Compile `source` into a runtime that is ready to run
+		pub fn load(source: &str) -> Runtime {
+			let mut compiler = Compiler::new(source);
+			compiler.compile().unwrap();
+			let baggage = compiler.baggage.iter().map(|b| b.unzip()).collect();
+			Runtime::new(baggage, None, compiler.scope)
+		}
+
+		/// Compile and run `source`, returning the runtime once it has finished
+		pub fn run(source: &str) -> RuntimeResult<Runtime> {
+			let mut runtime = load(source);
+			runtime.exec()?;
+			Ok(runtime)
+		}
+
+		/// A shared buffer that captures a runtime's output
+		#[derive(Clone, Default)]
+		pub struct Output(pub Arc<std::sync::Mutex<Vec<u8>>>);
+
+		impl Write for Output {
+			fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+				self.0.lock().unwrap().write(buf)
+			}
+			fn flush(&mut self) -> std::io::Result<()> {
+				Ok(())
+			}
+		}
+
+		/// Compile and run `source`, returning what it printed
+		pub fn output(source: &str) -> String {
+			let mut runtime = load(source);
+			let output = Output::default();
+			runtime.set_output(output.clone());
+			runtime.exec().unwrap();
+			let printed = output.0.lock().unwrap().clone();
+			String::from_utf8(printed).unwrap()
+		}
+
+		/// Get the value of the variable called `name`
+		pub fn var<'r>(runtime: &'r Runtime, name: &str) -> &'r Value {
+			&runtime.scope.vars.iter().rev().find(|v| v.name == name).unwrap().value
+		}
+
         #[macro_export]
         macro_rules! binop_test {
             ($op:tt, $i:expr) => {
@@ -275,7 +1023,8 @@ pub(crate) mod tests {
 			vars: vec![
 				Local {
 					depth: 0,
-					name: "asdf".into()
+					name: "asdf".into(),
+					mutable: false,
 				}
 			]
 		};
@@ -297,4 +1046,446 @@ pub(crate) mod tests {
 			]
 		})
 	}
+
+	#[test]
+	fn for_loop() {
+		let runtime = run("let mut sum = 0; for x in [1, 2, 3] { sum = sum + x; }").unwrap();
+		assert_eq!(var(&runtime, "sum"), &Value::VNumber(6.));
+
+		let runtime = run("let mut sum = 0; for i in 0..5 { sum = sum + i; }").unwrap();
+		assert_eq!(var(&runtime, "sum"), &Value::VNumber(10.));
+
+		let runtime = run("let mut n = 0; for c in \"héllo\" { n = n + 1; } let mut last = nil; for k in {\"b\": 1, \"a\": 2} { last = k; }").unwrap();
+		assert_eq!(var(&runtime, "n"), &Value::VNumber(5.));
+		assert_eq!(var(&runtime, "last"), &Value::VString("b".into()));
+	}
+
+	#[test]
+	fn for_loop_across_bags() {
+		// Enough statements in the loop body to push it over several bags
+		let body = "sum = sum + i; ".repeat(40);
+		let runtime = run(&format!("let mut sum = 0; for i in 0..3 {{ {} }}", body)).unwrap();
+		assert_eq!(var(&runtime, "sum"), &Value::VNumber(120.));
+	}
+
+	#[test]
+	fn not_iterable() {
+		assert_eq!(
+			run("for x in true {}").err(),
+			Some(RuntimeError::Iteration(IterationError::NotIterable("bool")))
+		);
+	}
+
+	#[test]
+	fn interpolation() {
+		let runtime = run(r#"
+			let name = "blush";
+			let count = 2;
+			let msg = "Hello, {name}! You have {count + 1} items {{escaped}}";
+			let nested = "{ "inner {count}" }";
+		"#).unwrap();
+		assert_eq!(var(&runtime, "msg"), &Value::VString("Hello, blush! You have 3 items {escaped}".into()));
+		assert_eq!(var(&runtime, "nested"), &Value::VString("inner 2".into()));
+	}
+
+	#[test]
+	fn format_values() {
+		let runtime = run(r#"
+			let s = "{nil} {true} {1.5} {[1, "a"]} { {"k": [nil]} } {0..3}";
+		"#).unwrap();
+		assert_eq!(var(&runtime, "s"), &Value::VString(r#"nil true 1.5 [1, "a"] {"k": [nil]} 0..3"#.into()));
+	}
+
+	#[test]
+	fn functions() {
+		let runtime = run(r#"
+			fn add(a, b) { return a + b; }
+			let sum = 1 + add(2, add(3, 4));
+
+			fn greet(name, greeting = "hi", ...rest) {
+				return "{greeting}, {name} {rest}";
+			}
+			let a = greet("bob");
+			let b = greet("bob", "yo", 1, 2);
+			let c = greet(greeting: "hey", name: "amy");
+
+			let mut count = 0;
+			fn counter(n = count) { return n; }
+			count = 5;
+			let d = counter();
+
+			fn nothing() {}
+			let e = nothing();
+		"#).unwrap();
+		assert_eq!(var(&runtime, "sum"), &Value::VNumber(10.));
+		assert_eq!(var(&runtime, "a"), &Value::VString("hi, bob []".into()));
+		assert_eq!(var(&runtime, "b"), &Value::VString("yo, bob [1, 2]".into()));
+		assert_eq!(var(&runtime, "c"), &Value::VString("hey, amy []".into()));
+		assert_eq!(var(&runtime, "d"), &Value::VNumber(5.));
+		assert_eq!(var(&runtime, "e"), &Value::VNil);
+		assert!(runtime.frames.is_empty());
+	}
+
+	#[test]
+	fn call_errors() {
+		let error = |source| run(source).err().unwrap();
+		assert_eq!(error("fn f(a, b = 1) {} f();"), RuntimeError::Call(CallError::Arity {
+			function: "f".into(),
+			min: 1,
+			max: Some(2),
+			received: 0,
+		}));
+		assert_eq!(error("fn f(...xs) {} f(x: 1);"), RuntimeError::Call(CallError::UnknownArgument {
+			function: "f".into(),
+			name: "x".into(),
+		}));
+		assert_eq!(error("fn f(a) {} f(1, a: 2);"), RuntimeError::Call(CallError::DuplicateArgument {
+			function: "f".into(),
+			name: "a".into(),
+		}));
+		assert_eq!(error("let x = 1; x();"), RuntimeError::Call(CallError::NotCallable("num")));
+	}
+
+	#[test]
+	fn natives() {
+		let source = r#"
+			let sum = sum_all(1, 2, 3);
+			let alias = sum_all;
+			let twice = alias(sum, sum);
+			let mut shadowed = nil;
+			for sum_all in [5] { shadowed = sum_all; }
+			let after = sum_all();
+		"#;
+		let mut compiler = Compiler::new(source);
+		compiler.declare_native("sum_all").declare_native("fail");
+		compiler.compile().unwrap();
+		let baggage = compiler.baggage.iter().map(|b| b.unzip()).collect();
+		let mut runtime = Runtime::new(baggage, None, compiler.scope);
+		runtime.register_native("sum_all", |args| {
+			args.iter().try_fold(Value::VNumber(0.), |acc, v| acc + v.clone())
+		});
+		runtime.exec().unwrap();
+		assert_eq!(var(&runtime, "sum"), &Value::VNumber(6.));
+		assert_eq!(var(&runtime, "alias"), &Value::VNative("sum_all".into()));
+		assert_eq!(var(&runtime, "twice"), &Value::VNumber(12.));
+		assert_eq!(var(&runtime, "shadowed"), &Value::VNumber(5.));
+		assert_eq!(var(&runtime, "after"), &Value::VNumber(0.));
+	}
+
+	#[test]
+	fn native_errors() {
+		let run_with = |source| {
+			let mut compiler = Compiler::new(source);
+			compiler.declare_native("fail").declare_native("missing");
+			compiler.compile().unwrap();
+			let baggage = compiler.baggage.iter().map(|b| b.unzip()).collect();
+			let mut runtime = Runtime::new(baggage, None, compiler.scope);
+			runtime.register_native("fail", |_| Err(RuntimeError::ExternalError("Fail".into(), "failed".into())));
+			runtime.exec().err().unwrap()
+		};
+		assert_eq!(run_with("fail();"), RuntimeError::ExternalError("Fail".into(), "failed".into()));
+		assert_eq!(run_with("missing();"), RuntimeError::Call(CallError::UndefinedNative("missing".into())));
+		assert_eq!(run_with("fail(x: 1);"), RuntimeError::Call(CallError::UnknownArgument {
+			function: "fail".into(),
+			name: "x".into(),
+		}));
+	}
+
+	#[test]
+	fn print() {
+		assert_eq!(output(r#"print("a", 1); println("", [true, "b"]); println(); print(nil);"#), "a 1 [true, \"b\"]\n\nnil");
+		assert_eq!(output(r#"fn f(x) { println("got {x}"); } f(1); f({"k": 0..2});"#), "got 1\ngot {\"k\": 0..2}\n");
+	}
+
+	#[test]
+	fn exit() {
+		let runtime = run(r#"
+			let mut last = 0;
+			fn stop() { exit(3); }
+			for i in 0..10 { last = i; stop(); }
+		"#).unwrap();
+		assert_eq!(runtime.exit_code, Some(3));
+		assert_eq!(var(&runtime, "last"), &Value::VNumber(0.));
+		assert_eq!(run("let x = 1;").unwrap().exit_code, None);
+		assert_eq!(run("exit(2147483520);").unwrap().exit_code, Some(2147483520));
+		for code in ["2147483648", "math.pow(2, 40)"] {
+			assert_eq!(
+				run(&format!("exit({});", code)).err(),
+				Some(RuntimeError::Call(CallError::InvalidArgument {
+					function: "exit".into(),
+					reason: "the exit code is too large",
+				}))
+			);
+		}
+		assert!(matches!(run("exit(math.pow(10, 30));"), Err(RuntimeError::Call(CallError::ArgumentType { .. }))));
+	}
+
+	#[test]
+	fn fuel() {
+		let mut runtime = load("let mut n = 0; for i in 0..100 { n = n + 1; }");
+		runtime.fuel = Some(50);
+		let mut refuels = 0;
+		while let Err(e) = runtime.exec() {
+			assert_eq!(e, RuntimeError::Limit(LimitError::OutOfFuel));
+			runtime.fuel = Some(50);
+			refuels += 1;
+		}
+		assert!(refuels > 10);
+		assert_eq!(var(&runtime, "n"), &Value::VNumber(100.));
+	}
+
+	#[test]
+	fn deadline_and_interrupt() {
+		let forever = "let mut n = 0; for i in 0..100000000 { n = n + 1; }";
+		let mut runtime = load(forever);
+		runtime.deadline = Some(Instant::now() + std::time::Duration::from_millis(20));
+		assert_eq!(runtime.exec(), Err(RuntimeError::Limit(LimitError::DeadlineExceeded)));
+
+		let mut runtime = load(forever);
+		let interrupt = runtime.interrupt_handle();
+		let thread = std::thread::spawn(move || {
+			std::thread::sleep(std::time::Duration::from_millis(20));
+			interrupt.store(true, Ordering::Relaxed);
+		});
+		assert_eq!(runtime.exec(), Err(RuntimeError::Limit(LimitError::Interrupted)));
+		thread.join().unwrap();
+
+		// The interrupt is cleared once it has stopped execution
+		let mut runtime = load("let x = 1;");
+		runtime.interrupt_handle().store(true, Ordering::Relaxed);
+		assert_eq!(runtime.exec(), Err(RuntimeError::Limit(LimitError::Interrupted)));
+		assert_eq!(runtime.exec(), Ok(()));
+		assert_eq!(var(&runtime, "x"), &Value::VNumber(1.));
+	}
+
+	#[test]
+	fn memory_limits() {
+		let mut runtime = load(r#"let mut s = "ab"; for i in 0..40 { s = "{s}{s}"; }"#);
+		runtime.max_heap = Some(1 << 20);
+		match runtime.exec() {
+			Err(RuntimeError::OutOfMemory(MemoryError::Heap { limit, used })) => assert!(used > limit),
+			r => panic!("expected the heap to run out, got {:?}", r),
+		}
+
+		let mut runtime = load(r#"let s = "abc".repeat(1000000000);"#);
+		runtime.max_heap = Some(1 << 20);
+		assert!(matches!(runtime.exec(), Err(RuntimeError::OutOfMemory(MemoryError::Heap { .. }))));
+
+		// Results are reserved before they are built, so a result that won't fit never reaches the heap
+		let scripts = [
+			r#"s = "{s}{s}";"#,
+			r#"s = s.replace("a", s);"#,
+			r#"s = s.join(["a", "b", "c"]);"#,
+			r#"s = re.replace("a", s, s);"#,
+		];
+		for script in scripts {
+			let mut runtime = load(&format!(r#"let mut s = "ab"; for i in 0..40 {{ {} }}"#, script));
+			runtime.max_heap = Some(1 << 20);
+			match runtime.exec() {
+				Err(RuntimeError::OutOfMemory(MemoryError::Heap { used, .. })) => assert!(runtime.heap_size() < used, "{}", script),
+				r => panic!("expected the heap to run out in {}, got {:?}", script, r),
+			}
+		}
+
+		let mut runtime = load("let mut s = 0; for i in 0..1000 { s = \"{i}\"; }");
+		runtime.max_heap = Some(1 << 12);
+		assert_eq!(runtime.exec(), Ok(()));
+
+		let mut runtime = load("fn forever(n) { return forever(n + 1); } forever(0);");
+		runtime.max_depth = Some(64);
+		assert_eq!(runtime.exec(), Err(RuntimeError::OutOfMemory(MemoryError::CallDepth(64))));
+		assert_eq!(runtime.frames.len(), 64);
+	}
+
+	#[test]
+	fn host_calls() {
+		let mut runtime = run("fn forever(n) { return forever(n + 1); } fn sum(n) { let mut t = 0; for i in 0..n { t = t + i; } return t; }").unwrap();
+		runtime.max_depth = Some(8);
+		assert_eq!(runtime.call_function("forever", &[Value::VNumber(0.)]), Err(RuntimeError::OutOfMemory(MemoryError::CallDepth(8))));
+		assert!(runtime.frames.is_empty());
+
+		// A call stopped by a limit keeps its frames, and can be resumed after other calls
+		runtime.fuel = Some(50);
+		assert_eq!(runtime.call_function("sum", &[Value::VNumber(100.)]), Err(RuntimeError::Limit(LimitError::OutOfFuel)));
+		assert_eq!(runtime.frames.len(), 1);
+		runtime.fuel = None;
+		assert_eq!(runtime.call_function("sum", &[Value::VNumber(10.)]), Ok(Value::VNumber(45.)));
+		assert_eq!(runtime.resume(), Ok(Value::VNumber(4950.)));
+		assert!(runtime.frames.is_empty() && runtime.host_calls.is_empty());
+
+		// Exiting drops paused calls, and nothing can be called afterwards
+		let mut runtime = run("fn sum(n) { let mut t = 0; for i in 0..n { t = t + i; } return t; } fn quit() { exit(4); }").unwrap();
+		runtime.fuel = Some(20);
+		assert_eq!(runtime.call_function("sum", &[Value::VNumber(100.)]), Err(RuntimeError::Limit(LimitError::OutOfFuel)));
+		runtime.fuel = None;
+		assert_eq!(runtime.call_function("quit", &[]), Ok(Value::VNil));
+		assert!(runtime.frames.is_empty() && runtime.host_calls.is_empty());
+		assert_eq!(runtime.call_function("sum", &[Value::VNumber(3.)]), Err(RuntimeError::Call(CallError::Exited(4))));
+
+		// With no call to go back to, the script itself is resumed
+		let program = crate::Engine::new().compile("let mut t = 0; for i in 0..10 { t = t + i; } t;").unwrap();
+		let mut runtime = Runtime::from_program(program);
+		runtime.fuel = Some(10);
+		assert_eq!(runtime.exec(), Err(RuntimeError::Limit(LimitError::OutOfFuel)));
+		runtime.fuel = None;
+		assert_eq!(runtime.resume(), Ok(Value::VNumber(45.)));
+	}
+
+	#[test]
+	fn observers() {
+		#[derive(Clone, Default)]
+		struct Trace(Arc<std::sync::Mutex<Vec<String>>>);
+		impl Observer for Trace {
+			fn instruction(&mut self, _: &State, opcode: u8) {
+				if opcode == Instruction::Call as u8 {
+					self.0.lock().unwrap().push("CALL".into());
+				}
+			}
+			fn call(&mut self, state: &State, function: &str) {
+				self.0.lock().unwrap().push(format!("call {} at depth {}", function, state.depth));
+			}
+			fn ret(&mut self, state: &State, function: &str, value: &Value) {
+				self.0.lock().unwrap().push(format!("{} returned {} at depth {}", function, value, state.depth));
+			}
+			fn write(&mut self, _: &State, _: u8, name: &str, value: &Value) {
+				self.0.lock().unwrap().push(format!("{} = {}", name, value));
+			}
+			fn error(&mut self, _: &State, error: &RuntimeError) {
+				self.0.lock().unwrap().push(format!("error {}", error));
+			}
+		}
+
+		let trace = Trace::default();
+		let mut runtime = load("fn twice(n) { return n * 2; } let mut x = twice(4); x = x + 1; twice(nil);");
+		runtime.set_observer(trace.clone());
+		assert!(runtime.exec().is_err());
+		assert_eq!(*trace.0.lock().unwrap(), [
+			"twice = <fn twice>",
+			"CALL",
+			"call twice at depth 1",
+			"n = 4",
+			// The parameter's slot gets back what it held before the call
+			"n = false",
+			"twice returned 8 at depth 0",
+			"x = 8",
+			"x = 9",
+			"CALL",
+			"call twice at depth 1",
+			"n = nil",
+			"error Arithmetic(TypeConflict)",
+		]);
+	}
+
+	#[test]
+	fn stepping() {
+		let program = crate::Engine::new().compile("let mut n = 0; for i in 0..10 { n = n + i; } n;").unwrap();
+		let mut runtime = Runtime::from_program(program.clone());
+		assert_eq!(runtime.step(), Status::Running);
+		let mut slices = 1;
+		let value = loop {
+			match runtime.run_for(5) {
+				Status::Yielded => slices += 1,
+				Status::Finished(value) => break value,
+				status => panic!("unexpected {:?}", status),
+			}
+		};
+		assert!(slices > 5);
+		assert_eq!(value, Value::VNumber(45.));
+		assert_eq!(runtime.step(), Status::Finished(Value::VNumber(45.)));
+
+		let mut runtime = Runtime::from_program(program);
+		let mut steps = 0;
+		while runtime.step() == Status::Running {
+			steps += 1;
+		}
+		assert!(steps > slices * 4);
+		assert_eq!(runtime.value(), Value::VNumber(45.));
+
+		let mut runtime = load("let x = 1; for i in 0 {}");
+		assert_eq!(
+			runtime.run_for(100),
+			Status::Error(RuntimeError::Iteration(IterationError::NotIterable("num")))
+		);
+	}
+
+	#[test]
+	fn iterator_objects() {
+		struct Countdown(std::sync::Mutex<u8>);
+		impl HostObject for Countdown {
+			fn type_name(&self) -> &'static str { "countdown" }
+			fn call(&self, name: &str, _: &[Value]) -> RuntimeResult<Value> {
+				assert_eq!(name, "next");
+				let mut n = self.0.lock().unwrap();
+				let result = BTreeMap::from([
+					("done".to_string(), Value::VBool(*n == 0)),
+					("value".to_string(), Value::VNumber(*n as f32)),
+				]);
+				*n = n.saturating_sub(1);
+				Ok(Value::VMap(result))
+			}
+		}
+
+		let mut engine = crate::Engine::new();
+		engine.register_native("countdown", |_| Ok(Value::VHost(Host::new(Countdown(3.into())))));
+		engine.register_native("halves", |args| match &args[0] {
+			Value::VMap(it) => {
+				let n = it.get("state").cloned().unwrap_or(Value::VNumber(8.));
+				Ok(Value::VMap(BTreeMap::from([
+					("done".to_string(), Value::VBool(n == Value::VNumber(1.))),
+					("value".to_string(), n.clone()),
+					("state".to_string(), (n / Value::VNumber(2.))?),
+				])))
+			}
+			v => panic!("expected the iterator object, got {}", v),
+		});
+		let result = engine.eval(r#"
+			fn digits(it) { return {"done": it.state == it.end, "value": it.state * 2, "state": it.state + 1}; }
+			let mut total = 0;
+			let mut inner = 0;
+			for x in iterator({"next": digits, "state": 1, "end": 5}) {
+				for y in [1, nil] { inner = inner + 1; }
+				total = total + x;
+			}
+			fn nils(it) { return {"done": it.state == 2, "state": it.state + 1}; }
+			let mut seen = "";
+			for n in iterator({"next": nils, "state": 0}) { seen = "{seen}{n} "; }
+			for c in countdown() { seen = "{seen}{c}"; }
+			for h in iterator({"next": halves}) { seen = "{seen} {h}"; }
+			for k in {"next": digits} { seen = "{seen} {k}"; }
+			"{total} {inner} {seen}";
+		"#);
+		assert_eq!(result, Ok(Value::VString("20 8 nil nil 321 8 4 2 next".into())));
+
+		assert_eq!(
+			engine.eval(r#"fn bad(it) { return 1; } for x in iterator({"next": bad}) {}"#),
+			Err(crate::error::engine::EngineError::Runtime(RuntimeError::Iteration(IterationError::InvalidResult("num"))))
+		);
+		assert!(matches!(
+			engine.eval(r#"iterator({"state": 0});"#),
+			Err(crate::error::engine::EngineError::Runtime(RuntimeError::Call(CallError::InvalidArgument { .. })))
+		));
+	}
+
+	#[test]
+	fn comparisons() {
+		let runtime = run(r#"
+			let a: bool = 1 < 2;
+			let b = 2 >= 3;
+			let c = "x" == "x";
+			let d = 1 != 1;
+			let e = !(1 > 2);
+			let f = 2 <= 2;
+		"#).unwrap();
+		for (name, value) in [("a", true), ("b", false), ("c", true), ("d", false), ("e", true), ("f", true)] {
+			assert_eq!(var(&runtime, name), &Value::VBool(value), "{}", name);
+		}
+		assert_eq!(crate::Engine::new().eval("1 < 2;"), Ok(Value::VBool(true)));
+	}
+
+	#[test]
+	fn undecodable_constants() {
+		let bag = util::make_bag(vec![Instruction::Const as u8, 0, 3, 0], vec![9, 9, 9]);
+		let mut runtime = Runtime::new(vec![bag], None, Default::default());
+		assert!(matches!(runtime.exec(), Err(RuntimeError::Bytecode(_))));
+	}
 }
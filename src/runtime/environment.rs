@@ -1,7 +1,7 @@
 use crate::Value;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, Default)]
+#[derive(Clone, Debug, PartialEq, Default, Deserialize, Serialize)]
 pub struct Variable {
     pub name: String,
     pub value: Value,
@@ -12,6 +12,8 @@ pub struct Variable {
 pub struct Local {
     pub name: String,
     pub depth: u8,
+    /// Whether the variable was declared with `let mut`, and can be reassigned
+    pub mutable: bool,
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
@@ -26,7 +28,7 @@ pub type RuntimeScope = RawScope<Variable>;
 
 impl From<CompilerScope> for RuntimeScope {
     fn from(cs: CompilerScope) -> Self {
-        return RawScope {
+        RawScope {
             vars: cs
                 .vars
                 .iter()
@@ -38,6 +40,6 @@ impl From<CompilerScope> for RuntimeScope {
                 .collect::<Vec<_>>(),
             num_vars: cs.num_vars,
             depth: 0,
-        };
+        }
     }
 }
@@ -0,0 +1,132 @@
+/*
+	# Functions
+	A function's code is compiled inline, behind a jump that skips over it, and the function value records
+	where the code starts. Calling a function (CALL) pushes a [Frame] and jumps to the start of its code,
+	and RETURN pops the frame and continues after the call.
+
+	## Frames
+	Variables live in slots which are fixed at compile time, so a call saves the values of all of the
+	function's slots, along with the caller's registers, and restores them on return. This keeps recursive
+	calls from trampling over each other.
+
+	## Parameters
+	Arguments are matched to parameters positionally first, then by name. Extra positional arguments are
+	collected into the rest parameter (`...rest`) if there is one. Parameters with defaults that weren't
+	given are left for the function's prologue, which evaluates the defaults at call time:
+	1 Missing 0   If no argument was given for V(0), IC skips the jump
+	2 Jump  0 6   Otherwise, skip the default
+	3 ...         default value, into R(0)
+	4 Let   0 0   V(0) = R(0)
+*/
+
+use std::collections::BTreeMap;
+
+use crate::{Value, error::runtime::*};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Function {
+	pub name: String,
+	/// The bag and offset that the function's code starts at
+	pub entry: (u8, u8),
+	pub params: Vec<Param>,
+	/// The slots of the variables declared inside of the function, including its parameters
+	pub slots: (u8, u8),
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Param {
+	pub name: String,
+	pub slot: u8,
+	pub default: bool,
+	pub rest: bool,
+}
+
+/// The state of the caller, saved while a function runs
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Frame {
+	pub function: String,
+	/// The bag and IC of the call instruction's last byte
+	pub return_to: (usize, usize),
+	/// The caller's register that the return value is stored in
+	pub store: u8,
+	pub registers: Vec<Value>,
+	/// The function's slots, and the values they held before the call
+	pub slots: (u8, u8),
+	pub saved: Vec<Value>,
+	/// The slots of the parameters that were given an argument
+	pub given: Vec<u8>,
+	/// The caller's register holding the iterator, if the function is an iterator's `next`, so that
+	/// returning finishes the caller's NEXT
+	pub iterating: Option<u8>,
+}
+
+/// A call made by the host with [crate::Runtime::call_function], kept until it returns
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct HostCall {
+	/// How many frames there were before the call
+	pub depth: usize,
+	/// The bag and IC that execution was at when the host made the call
+	pub position: (usize, usize),
+	/// The value of R(0), which the function's return value is stored in until it is handed to the host
+	pub kept: Value,
+}
+
+impl Function {
+	/// Match the arguments of a call to the function's parameters, returning the slots to bind and the
+	/// values to bind them to
+	pub fn bind(&self, positional: Vec<Value>, named: BTreeMap<String, Value>) -> RuntimeResult<Vec<(u8, Value)>> {
+		let received = positional.len() + named.len();
+		let arity = || RuntimeError::Call(CallError::Arity {
+			function: self.name.clone(),
+			min: self.params.iter().filter(|p| !p.default && !p.rest).count(),
+			max: if self.params.iter().any(|p| p.rest) {
+				None
+			} else {
+				Some(self.params.len())
+			},
+			received,
+		});
+
+		let mut bound: Vec<(u8, Value)> = vec![];
+		let mut positional = positional.into_iter();
+		for param in self.params.iter().filter(|p| !p.rest) {
+			match positional.next() {
+				Some(v) => bound.push((param.slot, v)),
+				None => break,
+			}
+		}
+
+		let extra: Vec<Value> = positional.collect();
+		match self.params.iter().find(|p| p.rest) {
+			Some(rest) => bound.push((rest.slot, Value::VList(extra))),
+			None if !extra.is_empty() => return Err(arity()),
+			None => {}
+		}
+
+		for (name, v) in named {
+			let param = self.params
+				.iter()
+				.find(|p| p.name == name && !p.rest)
+				.ok_or_else(|| RuntimeError::Call(CallError::UnknownArgument {
+					function: self.name.clone(),
+					name: name.clone(),
+				}))?;
+			if bound.iter().any(|b| b.0 == param.slot) {
+				return Err(RuntimeError::Call(CallError::DuplicateArgument {
+					function: self.name.clone(),
+					name,
+				}));
+			}
+			bound.push((param.slot, v));
+		}
+
+		let missing = self.params
+			.iter()
+			.any(|p| !p.default && !bound.iter().any(|b| b.0 == p.slot));
+		if missing {
+			return Err(arity());
+		}
+		Ok(bound)
+	}
+}
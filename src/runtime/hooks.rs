@@ -0,0 +1,39 @@
+/*
+	# Execution Hooks
+	An [Observer] is told about what a script is doing as it runs, for tracing, profiling or debugging
+	without touching the interpreter loop. Every hook has an empty default, so observers only implement
+	the events they care about, and each hook is given a [State] with where execution is up to.
+
+	The observer is taken out of the runtime while a hook runs, so hooks can't reach the runtime itself.
+*/
+
+use crate::{Value, error::runtime::RuntimeError};
+
+/// Where execution is up to when a hook is called
+#[derive(Debug)]
+pub struct State<'r> {
+	pub bag: usize,
+	pub ic: usize,
+	pub registers: &'r [Value],
+	/// How many script functions deep execution is
+	pub depth: usize,
+}
+
+#[allow(unused_variables)]
+pub trait Observer: Send {
+	/// The instruction `opcode` is about to be run
+	fn instruction(&mut self, state: &State, opcode: u8) {}
+
+	/// The script function `function` was called, and execution is now at its start
+	fn call(&mut self, state: &State, function: &str) {}
+
+	/// The script function `function` returned `value`, and execution is back in the caller
+	fn ret(&mut self, state: &State, function: &str, value: &Value) {}
+
+	/// The variable `name` in slot `slot` was set to `value`. This includes parameters being bound when a
+	/// function is called, and a function's slots getting back their old values when it returns.
+	fn write(&mut self, state: &State, slot: u8, name: &str, value: &Value) {}
+
+	/// Execution stopped with `error`
+	fn error(&mut self, state: &State, error: &RuntimeError) {}
+}
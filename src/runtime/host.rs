@@ -0,0 +1,132 @@
+/*
+	# Host Objects
+	Host objects are Rust values which are handed to scripts by reference, i.e. a database handle or the
+	context of a request. Scripts can't look inside of them, but can call their methods (`ctx.header("x")`)
+	and read their properties (`ctx.path`), which the runtime passes on to the [HostObject]. Runtimes can be
+	moved between threads, so host objects must be `Send + Sync`.
+*/
+
+use std::{fmt, sync::Arc};
+
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Value, error::runtime::*};
+
+pub trait HostObject: Send + Sync {
+	/// The name of the object's type, as scripts see it
+	fn type_name(&self) -> &'static str;
+
+	/// Call the method `name`. Objects without the method should return [CallError::NoMethod].
+	fn call(&self, name: &str, args: &[Value]) -> RuntimeResult<Value> {
+		let _ = args;
+		Err(RuntimeError::Call(CallError::NoMethod {
+			ty: self.type_name(),
+			name: name.into(),
+		}))
+	}
+
+	/// Get the property `name`, or `None` if the object doesn't have it
+	fn property(&self, name: &str) -> Option<Value> {
+		let _ = name;
+		None
+	}
+}
+
+/// A shared reference to a host object. Copies of the value refer to the same object, and are only equal
+/// to each other.
+#[derive(Clone)]
+pub struct Host(pub Arc<dyn HostObject>);
+
+impl Host {
+	pub fn new(object: impl HostObject + 'static) -> Self {
+		Self(Arc::new(object))
+	}
+}
+
+impl PartialEq for Host {
+	fn eq(&self, other: &Self) -> bool {
+		Arc::ptr_eq(&self.0, &other.0)
+	}
+}
+
+impl fmt::Debug for Host {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Host({})", self.0.type_name())
+	}
+}
+
+/// Host objects only exist while the host is running, so they can't be stored
+impl Serialize for Host {
+	fn serialize<S: Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+		Err(ser::Error::custom(format!("{} objects can't be serialized", self.0.type_name())))
+	}
+}
+
+impl<'de> Deserialize<'de> for Host {
+	fn deserialize<D: Deserializer<'de>>(_: D) -> Result<Self, D::Error> {
+		Err(de::Error::custom("host objects can't be deserialized"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{collections::BTreeMap, sync::atomic::{AtomicUsize, Ordering}};
+
+	use super::*;
+	use crate::{Engine, error::engine::EngineError};
+
+	struct Request {
+		headers: BTreeMap<String, String>,
+		reads: AtomicUsize,
+	}
+
+	impl HostObject for Request {
+		fn type_name(&self) -> &'static str {
+			"request"
+		}
+
+		fn call(&self, name: &str, args: &[Value]) -> RuntimeResult<Value> {
+			match (name, args) {
+				("header", [Value::VString(key)]) => {
+					self.reads.fetch_add(1, Ordering::Relaxed);
+					Ok(self.headers.get(key).cloned().map(Value::VString).unwrap_or(Value::VNil))
+				}
+				_ => Err(RuntimeError::Call(CallError::NoMethod { ty: "request", name: name.into() })),
+			}
+		}
+
+		fn property(&self, name: &str) -> Option<Value> {
+			match name {
+				"path" => Some(Value::VString("/index".into())),
+				_ => None,
+			}
+		}
+	}
+
+	#[test]
+	fn methods_and_properties() {
+		let request = Arc::new(Request {
+			headers: BTreeMap::from([("x".into(), "1".into())]),
+			reads: AtomicUsize::new(0),
+		});
+		let host = Host(request.clone());
+		let mut engine = Engine::new();
+		engine.register_native("request", move |_| Ok(Value::VHost(host.clone())));
+
+		assert_eq!(
+			engine.eval(r#"let ctx = request(); "{ctx.path} {ctx.header("x")} {ctx.header("y")} {ctx}";"#),
+			Ok(Value::VString("/index 1 nil <request>".into()))
+		);
+		assert_eq!(request.reads.load(Ordering::Relaxed), 2);
+		assert_eq!(engine.eval("let a = request(); a;"), engine.eval("request();"));
+		assert_eq!(engine.eval(r#"let m = {"k": {"j": 2}}; m.k.j + 1;"#), Ok(Value::VNumber(3.)));
+
+		let error = |source| match engine.eval(source) {
+			Err(EngineError::Runtime(RuntimeError::Call(e))) => e,
+			res => panic!("expected a call error, got {:?}", res),
+		};
+		assert_eq!(error("request().method;"), CallError::NoProperty { ty: "request", name: "method".into() });
+		assert_eq!(error("request().send();"), CallError::NoMethod { ty: "request", name: "send".into() });
+		assert_eq!(error("let n = 1; n.x;"), CallError::NoProperty { ty: "num", name: "x".into() });
+	}
+}
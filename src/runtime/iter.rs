@@ -0,0 +1,90 @@
+/*
+	# Iteration Protocol
+	`for x in v` asks the runtime to turn `v` into an [Iter] (ITER), then repeatedly asks the iterator for
+	its next value (NEXT) until it reports that it is done. Lists, maps, strings and ranges are iterated
+	natively:
+	- lists yield their elements
+	- maps yield their keys in order
+	- strings yield their characters
+	- ranges yield every number from the start up to (but not including) the end
+
+	Any other iterator is an object with a `next` member: a map holding a function under `"next"` that has
+	been made into an iterator with the `iterator` builtin, or a host object with a `next` method. NEXT
+	calls it for every item, passing maps to their own `next`, and it returns a result map:
+	- `{"done": true}` ends the loop
+	- otherwise the loop gets the result's `"value"`, which may be nil
+	- a `"state"` in the result replaces the object's `"state"`, so the next call can pick up from there
+	```blush
+	fn count(it) { return {"done": it.state == 3, "value": it.state, "state": it.state + 1}; }
+	for x in iterator({"next": count, "state": 0}) { println(x); }
+	```
+	Host objects keep their own state, so their `next` is called without arguments. A script function is
+	run as a normal call, and RETURN finishes the NEXT that called it.
+
+	Iterators advance in place inside the register that holds them, so nested loops each own a register.
+*/
+
+use crate::{Value, error::runtime::*};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Iter {
+	List(Vec<Value>, usize),
+	Keys(Vec<String>, usize),
+	Chars(Vec<char>, usize),
+	Range(f32, f32),
+	/// An object with a `next` member, which the runtime calls
+	Object(Value),
+}
+
+impl Iterator for Iter {
+	type Item = Value;
+
+	/// Advance the iterator, returning `None` once it is done.
+	/// Objects are advanced by the runtime instead, as calling `next` may run script code.
+	fn next(&mut self) -> Option<Value> {
+		match self {
+			Self::List(items, idx) => {
+				let item = items.get(*idx).cloned();
+				*idx += 1;
+				item
+			}
+			Self::Keys(keys, idx) => {
+				let key = keys.get(*idx).cloned().map(Value::VString);
+				*idx += 1;
+				key
+			}
+			Self::Chars(chars, idx) => {
+				let c = chars.get(*idx).map(|c| Value::VString(c.to_string()));
+				*idx += 1;
+				c
+			}
+			Self::Range(current, end) => {
+				if current < end {
+					let n = *current;
+					*current += 1.;
+					Some(Value::VNumber(n))
+				} else {
+					None
+				}
+			}
+			Self::Object(_) => None,
+		}
+	}
+}
+
+impl TryFrom<Value> for Iter {
+	type Error = RuntimeError;
+
+	fn try_from(value: Value) -> RuntimeResult<Self> {
+		Ok(match value {
+			Value::VList(items) => Self::List(items, 0),
+			Value::VMap(map) => Self::Keys(map.into_keys().collect(), 0),
+			Value::VHost(host) => Self::Object(Value::VHost(host)),
+			Value::VString(s) => Self::Chars(s.chars().collect(), 0),
+			Value::VRange(start, end) => Self::Range(start, end),
+			Value::VIter(iter) => *iter,
+			v => return Err(RuntimeError::Iteration(IterationError::NotIterable(v.type_name()))),
+		})
+	}
+}
@@ -0,0 +1,24 @@
+/*
+	# Native Functions
+	Native functions are written in Rust and registered with the runtime by the host application. The
+	compiler only needs to know their names: a native name that isn't shadowed by a variable compiles to a
+	constant [Value::VNative], and calling that value looks the function up in the runtime by name.
+
+	Natives take their arguments positionally, as a slice, and don't accept named arguments. They must be
+	`Send + Sync`, so that one engine can hand the same natives to runtimes on many threads.
+
+	## Builtins
+	A few natives are built into the runtime because they need access to it, such as `print` writing to the
+	runtime's output. The compiler always knows about them, and a host native registered under the same name
+	replaces the builtin. The standard library's modules are builtins too (see [super::stdlib]).
+*/
+
+use std::sync::Arc;
+
+use crate::{Value, error::runtime::*};
+
+/// A function implemented by the host
+pub type NativeFn = Arc<dyn Fn(&[Value]) -> RuntimeResult<Value> + Send + Sync>;
+
+/// The names of the natives which are built into the runtime
+pub const BUILTINS: &[&str] = &["print", "println", "exit", "iterator"];
@@ -0,0 +1,143 @@
+/*
+	# Snapshots
+	A paused runtime can be saved with [Runtime::snapshot] and picked back up later, possibly in another
+	process, with [Runtime::restore]. The snapshot holds the program along with everything the
+	script has changed: variables, registers, call frames, unfinished host calls, where execution is up
+	to and the state of the random generator, so that execution continues exactly where it left off.
+
+	What the host set up isn't saved, as it can't always be: natives, the output, the clock and the
+	capabilities of the `fs` and `env` modules have to be set up again on the restored runtime.
+	Host objects can't be saved either, so a script holding one can't be snapshotted.
+
+	Snapshots start with the version of blush that took them, and are only restored by the same version,
+	as the bytecode and values they hold may not mean the same thing to another.
+*/
+
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{BLUSH_VER, Value, Program, error::runtime::*};
+use super::*;
+
+#[derive(Deserialize, Serialize)]
+struct Snapshot {
+	program: Program,
+	scope: RuntimeScope,
+	registers: Vec<Value>,
+	ic: usize,
+	current_bag: usize,
+	frames: Vec<Frame>,
+	host_calls: Vec<HostCall>,
+	rng: ChaCha8Rng,
+	args: Vec<String>,
+	exit_code: Option<i32>,
+	fuel: Option<u64>,
+	max_heap: Option<usize>,
+	max_depth: Option<usize>,
+}
+
+impl Runtime {
+	/// Save the state of the script, to be picked back up with [Runtime::restore]
+	pub fn snapshot(&self) -> RuntimeResult<Vec<u8>> {
+		let snapshot = Snapshot {
+			program: self.program.as_ref().clone(),
+			scope: self.scope.clone(),
+			registers: self.registers.clone(),
+			ic: self.ic,
+			current_bag: self.current_bag,
+			frames: self.frames.clone(),
+			host_calls: self.host_calls.clone(),
+			rng: self.rng.clone(),
+			args: self.args.clone(),
+			exit_code: self.exit_code,
+			fuel: self.fuel,
+			max_heap: self.max_heap,
+			max_depth: self.max_depth,
+		};
+		bincode::serialize(&(BLUSH_VER, snapshot)).map_err(|e| RuntimeError::ExternalError("bincode::Error".into(), e.to_string()))
+	}
+
+	/// Rebuild a runtime from a [Runtime::snapshot]. Anything set up by the host has to be set up again.
+	pub fn restore(bytes: &[u8]) -> RuntimeResult<Self> {
+		let error = |e: bincode::Error| RuntimeError::ExternalError("bincode::Error".into(), e.to_string());
+		// The version is read on its own first, as the rest may not decode under another version
+		let version: String = bincode::deserialize(bytes).map_err(error)?;
+		if version != BLUSH_VER {
+			return Err(RuntimeError::SnapshotVersion(version));
+		}
+		let (_, snapshot): (String, Snapshot) = bincode::deserialize(bytes).map_err(error)?;
+		let mut runtime = Runtime::from_program(Arc::new(snapshot.program));
+		runtime.scope = snapshot.scope;
+		runtime.registers = snapshot.registers;
+		runtime.ic = snapshot.ic;
+		runtime.current_bag = snapshot.current_bag;
+		runtime.frames = snapshot.frames;
+		runtime.host_calls = snapshot.host_calls;
+		runtime.rng = snapshot.rng;
+		runtime.args = snapshot.args;
+		runtime.exit_code = snapshot.exit_code;
+		runtime.fuel = snapshot.fuel;
+		runtime.max_heap = snapshot.max_heap;
+		runtime.max_depth = snapshot.max_depth;
+		Ok(runtime)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::runtime::tests::util::*;
+
+	#[test]
+	fn resumes_where_it_left_off() {
+		let source = r#"
+			fn double(n) { return n * 2; }
+			let mut total = 0;
+			let mut rolls = "";
+			for i in 0..20 {
+				total = total + double(i);
+				rolls = "{rolls}{random.int(1, 6)}";
+			}
+		"#;
+		let mut expected = load(source);
+		expected.seed(7);
+		expected.exec().unwrap();
+
+		let mut runtime = load(source);
+		runtime.seed(7);
+		// Stop inside of one of the calls to `double`
+		while runtime.frames.is_empty() {
+			runtime.fuel = Some(1);
+			assert_eq!(runtime.exec(), Err(RuntimeError::Limit(LimitError::OutOfFuel)));
+		}
+		let mut restored = Runtime::restore(&runtime.snapshot().unwrap()).unwrap();
+		assert_eq!(restored.fuel, Some(0));
+		restored.fuel = None;
+		restored.exec().unwrap();
+
+		assert_eq!(var(&restored, "total"), &Value::VNumber(380.));
+		assert_eq!(var(&restored, "rolls"), var(&expected, "rolls"));
+	}
+
+	#[test]
+	fn host_objects_and_bad_bytes() {
+		struct Handle;
+		impl HostObject for Handle {
+			fn type_name(&self) -> &'static str { "handle" }
+		}
+		let mut runtime = load("let x = 1;");
+		runtime.registers[0] = Value::VHost(Host::new(Handle));
+		assert!(matches!(runtime.snapshot(), Err(RuntimeError::ExternalError(..))));
+		assert!(matches!(Runtime::restore(&[1, 2, 3]), Err(RuntimeError::ExternalError(..))));
+	}
+
+	#[test]
+	fn other_versions() {
+		let bytes = load("let x = 1;").snapshot().unwrap();
+		let rest = &bytes[bincode::serialized_size(BLUSH_VER).unwrap() as usize..];
+		let mut old = bincode::serialize("0.0.1-pre_alpha").unwrap();
+		old.extend(rest);
+		assert!(matches!(Runtime::restore(&old), Err(RuntimeError::SnapshotVersion(v)) if v == "0.0.1-pre_alpha"));
+		assert!(Runtime::restore(&bytes).is_ok());
+	}
+}
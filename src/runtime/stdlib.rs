@@ -0,0 +1,129 @@
+/*
+	# Standard Library
+	The standard library is made up of modules of native functions and constants which are built into the
+	runtime. Members are accessed with `module.member`, which the compiler resolves ahead of time:
+	constants are inlined, and functions compile to a [Value::VNative] called `module.member`.
+
+	## Methods
+	Calling a method on a value (`s.upper()`) calls the function with the same name in the module named after
+	the value's type, with the value as the first argument. i.e. `s.upper()` is `str.upper(s)`.
+*/
+
+use crate::{Runtime, Value, error::runtime::*};
+
+mod env;
+mod fs;
+mod json;
+mod math;
+mod random;
+mod re;
+mod string;
+mod time;
+pub use env::EnvCapabilities;
+pub use fs::{Access, FsCapabilities};
+pub use re::Pattern;
+pub use time::{Clock, ManualClock, SystemClock};
+
+/// A function in the standard library. Unlike host natives, they have access to the runtime.
+pub type StdFn = fn(&mut Runtime, &[Value]) -> RuntimeResult<Value>;
+
+pub struct Module {
+	pub name: &'static str,
+	pub functions: &'static [(&'static str, StdFn)],
+	pub constants: fn() -> Vec<(&'static str, Value)>,
+}
+
+/// Something that a module's member refers to
+pub enum Member {
+	Function(StdFn),
+	Constant(Value),
+}
+
+pub const MODULES: &[Module] = &[env::ENV, fs::FS, json::JSON, math::MATH, random::RANDOM, re::RE, string::STR, time::TIME];
+
+/// Find the standard library module called `name`
+pub fn module(name: &str) -> Option<&'static Module> {
+	MODULES.iter().find(|m| m.name == name)
+}
+
+impl Module {
+	pub fn member(&self, name: &str) -> Option<Member> {
+		if let Some((_, f)) = self.functions.iter().find(|f| f.0 == name) {
+			return Some(Member::Function(*f));
+		}
+		(self.constants)()
+			.into_iter()
+			.find(|c| c.0 == name)
+			.map(|c| Member::Constant(c.1))
+	}
+}
+
+/// Find the standard library function with the full name `name`, i.e. `math.sqrt`
+pub fn function(name: &str) -> Option<StdFn> {
+	let (module, member) = name.split_once('.')?;
+	match self::module(module)?.member(member)? {
+		Member::Function(f) => Some(f),
+		Member::Constant(_) => None,
+	}
+}
+
+/// Check that a native was given between `min` and `max` arguments
+pub fn arity(function: &str, args: &[Value], min: usize, max: Option<usize>) -> RuntimeResult {
+	if args.len() < min || max.map(|max| args.len() > max).unwrap_or(false) {
+		Err(RuntimeError::Call(CallError::Arity {
+			function: function.into(),
+			min,
+			max,
+			received: args.len(),
+		}))
+	} else {
+		Ok(())
+	}
+}
+
+/// Get the number passed as an argument to a native
+pub fn number(function: &str, arg: &Value) -> RuntimeResult<f32> {
+	match arg {
+		Value::VNumber(n) => Ok(*n),
+		v => Err(RuntimeError::Call(CallError::ArgumentType {
+			function: function.into(),
+			expected: "num",
+			found: v.type_name(),
+		})),
+	}
+}
+
+/// Get the string passed as an argument to a native
+pub fn string<'v>(function: &str, arg: &'v Value) -> RuntimeResult<&'v str> {
+	match arg {
+		Value::VString(s) => Ok(s),
+		v => Err(RuntimeError::Call(CallError::ArgumentType {
+			function: function.into(),
+			expected: "str",
+			found: v.type_name(),
+		})),
+	}
+}
+
+/// Get the non-negative whole number passed as an argument to a native, for use as an index or count
+pub fn index(function: &str, arg: &Value) -> RuntimeResult<usize> {
+	match number(function, arg)? {
+		// `usize::MAX as f32` rounds up, so it doesn't fit itself
+		n if n >= 0. && n.fract() == 0. && n < usize::MAX as f32 => Ok(n as usize),
+		_ => Err(RuntimeError::Call(CallError::ArgumentType {
+			function: function.into(),
+			expected: "index",
+			found: "num",
+		})),
+	}
+}
+
+/// Get exactly `N` numbers passed as the arguments to a native
+pub fn numbers<const N: usize>(function: &str, args: &[Value]) -> RuntimeResult<[f32; N]> {
+	arity(function, args, N, Some(N))?;
+	let mut numbers = [0.; N];
+	for (n, arg) in numbers.iter_mut().zip(args) {
+		*n = number(function, arg)?;
+	}
+	Ok(numbers)
+}
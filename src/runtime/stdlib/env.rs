@@ -0,0 +1,80 @@
+use super::*;
+
+/// The script's arguments and environment. Environment variables can only be read if the host has allowed
+/// them in [Runtime::env].
+pub const ENV: Module = Module {
+	name: "env",
+	functions: &[
+		("args", args),
+		("get", get),
+	],
+	constants: Vec::new,
+};
+
+/// The environment variables that scripts are allowed to read. Nothing is allowed by default.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EnvCapabilities {
+	pub all: bool,
+	pub vars: Vec<String>,
+}
+
+impl EnvCapabilities {
+	pub fn allow(&mut self, var: impl Into<String>) -> &mut Self {
+		self.vars.push(var.into());
+		self
+	}
+
+	pub fn allow_all(&mut self) -> &mut Self {
+		self.all = true;
+		self
+	}
+
+	pub fn check(&self, var: &str) -> RuntimeResult {
+		if self.all || self.vars.iter().any(|v| v == var) {
+			Ok(())
+		} else {
+			Err(RuntimeError::Capability(CapabilityError::Env(var.into())))
+		}
+	}
+}
+
+/// The arguments that the script was run with
+fn args(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	arity("env.args", args, 0, Some(0))?;
+	Ok(Value::VList(runtime.args.iter().cloned().map(Value::VString).collect()))
+}
+
+/// The value of an environment variable, or nil if it isn't set
+fn get(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	arity("env.get", args, 1, Some(1))?;
+	let var = string("env.get", &args[0])?;
+	runtime.env.check(var)?;
+	Ok(std::env::var(var).map(Value::VString).unwrap_or(Value::VNil))
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::runtime::tests::util::*;
+	use super::*;
+
+	#[test]
+	fn args_and_vars() {
+		std::env::set_var("BLUSH_TEST_VAR", "set");
+		let mut runtime = load(r#"
+			let a = env.args();
+			let v = env.get("BLUSH_TEST_VAR");
+			let unset = env.get("BLUSH_TEST_UNSET");
+		"#);
+		runtime.args = vec!["a".into(), "b".into()];
+		runtime.env.allow("BLUSH_TEST_VAR").allow("BLUSH_TEST_UNSET");
+		runtime.exec().unwrap();
+		assert_eq!(var(&runtime, "a").to_string(), r#"["a", "b"]"#);
+		assert_eq!(var(&runtime, "v"), &Value::VString("set".into()));
+		assert_eq!(var(&runtime, "unset"), &Value::VNil);
+
+		assert_eq!(
+			run(r#"env.get("PATH");"#).err(),
+			Some(RuntimeError::Capability(CapabilityError::Env("PATH".into())))
+		);
+	}
+}
@@ -0,0 +1,219 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use super::*;
+
+/// Filesystem functions, which can only touch paths that the host has allowed in [Runtime::fs].
+pub const FS: Module = Module {
+	name: "fs",
+	functions: &[
+		("read_text", read_text),
+		("write_text", write_text),
+		("exists", exists),
+		("list_dir", list_dir),
+		("remove", remove),
+	],
+	constants: Vec::new,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+	ReadOnly,
+	ReadWrite,
+}
+
+/// The directories that scripts are allowed to access. Nothing is allowed by default.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FsCapabilities {
+	pub roots: Vec<(PathBuf, Access)>,
+}
+
+impl FsCapabilities {
+	/// Allow access to everything inside of `root`
+	pub fn allow(&mut self, root: impl Into<PathBuf>, access: Access) -> &mut Self {
+		self.roots.push((root.into(), access));
+		self
+	}
+
+	/// Resolve `path`, checking that it is inside of a root with enough access.
+	/// Paths are canonicalized first so that `..` and symlinks can't escape a root.
+	pub fn check(&self, path: &str, write: bool) -> RuntimeResult<PathBuf> {
+		let denied = || RuntimeError::Capability(if write {
+			CapabilityError::Write(path.into())
+		} else {
+			CapabilityError::Read(path.into())
+		});
+		let resolved = canonicalize(Path::new(path)).map_err(|_| denied())?;
+		let allowed = self.roots.iter().any(|(root, access)| {
+			(!write || *access == Access::ReadWrite)
+				&& fs::canonicalize(root).map(|root| resolved.starts_with(root)).unwrap_or(false)
+		});
+		if allowed {
+			Ok(resolved)
+		} else {
+			Err(denied())
+		}
+	}
+}
+
+/// How many dangling symlinks are followed before giving up, as a chain that long is probably a loop
+const MAX_LINKS: usize = 40;
+
+/// Canonicalize a path which may not exist yet, as long as its parent does.
+/// A dangling symlink is resolved to where it points, as that's where a write through it would go.
+fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+	let mut path = path.to_path_buf();
+	for _ in 0..MAX_LINKS {
+		match fs::canonicalize(&path) {
+			Err(e) if e.kind() == io::ErrorKind::NotFound => {
+				let name = path.file_name().ok_or(e)?;
+				let parent = match path.parent() {
+					Some(parent) if !parent.as_os_str().is_empty() => parent,
+					_ => Path::new("."),
+				};
+				let resolved = fs::canonicalize(parent)?.join(name);
+				match fs::symlink_metadata(&resolved) {
+					Ok(metadata) if metadata.file_type().is_symlink() => {
+						// Relative targets are relative to the link's directory, and `join` keeps absolute ones
+						let target = fs::read_link(&resolved)?;
+						path = resolved.parent().unwrap_or(Path::new("/")).join(target);
+					}
+					_ => return Ok(resolved),
+				}
+			}
+			res => return res,
+		}
+	}
+	Err(io::Error::other("too many levels of symbolic links"))
+}
+
+fn io_error(e: io::Error) -> RuntimeError {
+	RuntimeError::ExternalError("io::Error".into(), e.to_string())
+}
+
+fn path(runtime: &Runtime, function: &str, args: &[Value], extra: usize, write: bool) -> RuntimeResult<PathBuf> {
+	arity(function, args, 1 + extra, Some(1 + extra))?;
+	runtime.fs.check(string(function, &args[0])?, write)
+}
+
+fn read_text(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let path = path(runtime, "fs.read_text", args, 0, false)?;
+	let len = fs::metadata(&path).map_err(io_error)?.len();
+	runtime.reserve(usize::try_from(len).unwrap_or(usize::MAX))?;
+	fs::read_to_string(path).map(Value::VString).map_err(io_error)
+}
+
+fn write_text(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let path = path(runtime, "fs.write_text", args, 1, true)?;
+	let text = string("fs.write_text", &args[1])?;
+	fs::write(path, text).map_err(io_error)?;
+	Ok(Value::VNil)
+}
+
+fn exists(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let path = path(runtime, "fs.exists", args, 0, false)?;
+	Ok(Value::VBool(path.exists()))
+}
+
+/// The names of the entries in a directory, in order
+fn list_dir(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let path = path(runtime, "fs.list_dir", args, 0, false)?;
+	let mut names = vec![];
+	for entry in fs::read_dir(path).map_err(io_error)? {
+		let entry = entry.map_err(io_error)?;
+		names.push(entry.file_name().to_string_lossy().into_owned());
+	}
+	names.sort();
+	Ok(Value::VList(names.into_iter().map(Value::VString).collect()))
+}
+
+/// Remove a file or an empty directory
+fn remove(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let path = path(runtime, "fs.remove", args, 0, true)?;
+	if path.is_dir() {
+		fs::remove_dir(path)
+	} else {
+		fs::remove_file(path)
+	}
+	.map_err(io_error)?;
+	Ok(Value::VNil)
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::runtime::tests::util::*;
+	use super::*;
+
+	/// A fresh directory for a test to work in
+	fn scratch(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("blush-fs-{}-{}", name, std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+		dir
+	}
+
+	#[test]
+	fn read_write() {
+		let dir = scratch("read_write");
+		let source = format!(r#"
+			let dir = "{}";
+			fs.write_text("{{dir}}/a.txt", "hello");
+			let text = fs.read_text("{{dir}}/a.txt");
+			let before = fs.list_dir(dir);
+			fs.remove("{{dir}}/a.txt");
+			let exists = fs.exists("{{dir}}/a.txt");
+		"#, dir.display());
+		let mut runtime = load(&source);
+		runtime.fs.allow(&dir, Access::ReadWrite);
+		runtime.exec().unwrap();
+		assert_eq!(var(&runtime, "text"), &Value::VString("hello".into()));
+		assert_eq!(var(&runtime, "before"), &Value::VList(vec![Value::VString("a.txt".into())]));
+		assert_eq!(var(&runtime, "exists"), &Value::VBool(false));
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn denied() {
+		let dir = scratch("denied");
+		fs::write(dir.join("a.txt"), "hello").unwrap();
+		let file = dir.join("a.txt").display().to_string();
+		let outside = dir.join("../a.txt").display().to_string();
+
+		let mut runtime = load(&format!(r#"fs.read_text("{}");"#, file));
+		assert_eq!(runtime.exec(), Err(RuntimeError::Capability(CapabilityError::Read(file.clone()))));
+
+		let mut runtime = load(&format!(r#"fs.read_text("{}"); fs.write_text("{}", "");"#, file, file));
+		runtime.fs.allow(&dir, Access::ReadOnly);
+		assert_eq!(runtime.exec(), Err(RuntimeError::Capability(CapabilityError::Write(file))));
+
+		let mut runtime = load(&format!(r#"fs.exists("{}");"#, outside));
+		runtime.fs.allow(&dir, Access::ReadWrite);
+		assert_eq!(runtime.exec(), Err(RuntimeError::Capability(CapabilityError::Read(outside))));
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn dangling_symlinks() {
+		let dir = scratch("dangling_symlinks");
+		let root = dir.join("root");
+		fs::create_dir(&root).unwrap();
+		std::os::unix::fs::symlink(dir.join("escaped.txt"), root.join("out")).unwrap();
+		std::os::unix::fs::symlink("inside.txt", root.join("in")).unwrap();
+		std::os::unix::fs::symlink("loop", root.join("loop")).unwrap();
+
+		// A link to a file that doesn't exist yet is checked against where it points
+		for link in ["out", "loop"] {
+			let path = root.join(link).display().to_string();
+			let mut runtime = load(&format!(r#"fs.write_text("{}", "x");"#, path));
+			runtime.fs.allow(&root, Access::ReadWrite);
+			assert_eq!(runtime.exec(), Err(RuntimeError::Capability(CapabilityError::Write(path))));
+		}
+		assert!(!dir.join("escaped.txt").exists());
+
+		let mut runtime = load(&format!(r#"fs.write_text("{}", "x");"#, root.join("in").display()));
+		runtime.fs.allow(&root, Access::ReadWrite);
+		assert_eq!(runtime.exec(), Ok(()));
+		assert_eq!(fs::read_to_string(root.join("inside.txt")).unwrap(), "x");
+		fs::remove_dir_all(dir).unwrap();
+	}
+}
@@ -0,0 +1,138 @@
+use serde::Serialize;
+use serde_json::{Number, ser::PrettyFormatter};
+
+use super::*;
+
+pub const JSON: Module = Module {
+	name: "json",
+	functions: &[
+		("parse", parse),
+		("stringify", stringify),
+	],
+	constants: Vec::new,
+};
+
+/// Parse a JSON document. Objects become maps, arrays become lists and null becomes nil.
+fn parse(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	arity("json.parse", args, 1, Some(1))?;
+	let text = string("json.parse", &args[0])?;
+	let json: serde_json::Value = serde_json::from_str(text)
+		.map_err(|e| RuntimeError::Json(JsonError::Parse(e.to_string())))?;
+	from_json(json)
+}
+
+/// The most spaces that `json.stringify` indents by, as in JavaScript's `JSON.stringify`
+const MAX_INDENT: usize = 10;
+
+/// Write a value as JSON, indented by `indent` spaces (at most 10) if it is given
+fn stringify(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	arity("json.stringify", args, 1, Some(2))?;
+	runtime.reserve(args[0].heap_size())?;
+	let json = to_json(&args[0])?;
+	let text = match args.get(1) {
+		Some(indent) => {
+			let indent = " ".repeat(index("json.stringify", indent)?.min(MAX_INDENT));
+			let mut out = vec![];
+			let mut serializer = serde_json::Serializer::with_formatter(&mut out, PrettyFormatter::with_indent(indent.as_bytes()));
+			json.serialize(&mut serializer)
+				.map_err(|e| RuntimeError::ExternalError("serde_json::Error".into(), e.to_string()))?;
+			String::from_utf8_lossy(&out).into_owned()
+		}
+		None => json.to_string(),
+	};
+	Ok(Value::VString(text))
+}
+
+/// Convert JSON to a value. Like [crate::to_value], integers which can't be stored exactly are an error,
+/// while decimals are rounded to the nearest number.
+fn from_json(json: serde_json::Value) -> RuntimeResult<Value> {
+	use serde_json::Value as Json;
+	const EXACT: u64 = 1 << f32::MANTISSA_DIGITS;
+	Ok(match json {
+		Json::Null => Value::VNil,
+		Json::Bool(b) => Value::VBool(b),
+		Json::Number(n) => {
+			let exact = match (n.as_i64(), n.as_u64()) {
+				(Some(i), _) => i.unsigned_abs() <= EXACT,
+				(_, Some(u)) => u <= EXACT,
+				_ => n.as_f64().map(|f| (f as f32).is_finite()).unwrap_or(false),
+			};
+			if !exact {
+				return Err(RuntimeError::Json(JsonError::Precision(n.to_string())));
+			}
+			Value::VNumber(n.as_f64().unwrap_or(f64::NAN) as f32)
+		}
+		Json::String(s) => Value::VString(s),
+		Json::Array(items) => Value::VList(items.into_iter().map(from_json).collect::<RuntimeResult<_>>()?),
+		Json::Object(map) => Value::VMap(
+			map.into_iter()
+				.map(|(k, v)| Ok((k, from_json(v)?)))
+				.collect::<RuntimeResult<_>>()?,
+		),
+	})
+}
+
+/// Convert a value to JSON. Whole numbers are written as integers, and other numbers with the fewest
+/// digits that read back as the same number.
+fn to_json(value: &Value) -> RuntimeResult<serde_json::Value> {
+	use serde_json::Value as Json;
+	let unserialisable = || RuntimeError::Json(JsonError::Unserialisable(value.type_name()));
+	Ok(match value {
+		Value::VNil => Json::Null,
+		Value::VBool(b) => Json::Bool(*b),
+		Value::VNumber(n) if !n.is_finite() => return Err(unserialisable()),
+		Value::VNumber(n) if n.fract() == 0. && n.abs() < i64::MAX as f32 => Json::Number((*n as i64).into()),
+		Value::VNumber(n) => {
+			let shortest = n.to_string().parse::<f64>().map_err(|_| unserialisable())?;
+			Json::Number(Number::from_f64(shortest).ok_or_else(unserialisable)?)
+		}
+		Value::VString(s) => Json::String(s.clone()),
+		Value::VList(items) => Json::Array(items.iter().map(to_json).collect::<RuntimeResult<_>>()?),
+		Value::VMap(map) => Json::Object(
+			map.iter()
+				.map(|(k, v)| Ok((k.clone(), to_json(v)?)))
+				.collect::<RuntimeResult<_>>()?,
+		),
+		Value::VRange(..) | Value::VIter(_) | Value::VFunction(_) | Value::VNative(_) | Value::VRegex(_) | Value::VHost(_) => {
+			return Err(unserialisable())
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::runtime::tests::util::*;
+	use super::*;
+
+	#[test]
+	fn parse() {
+		let runtime = run(r#"
+			let v = json.parse("{{\"a\": [1, 2.5, null, true], \"b\": {{\"c\": \"d\"}}}}");
+		"#).unwrap();
+		assert_eq!(var(&runtime, "v").to_string(), r#"{"a": [1, 2.5, nil, true], "b": {"c": "d"}}"#);
+		assert!(matches!(run(r#"json.parse("{{");"#).err(), Some(RuntimeError::Json(JsonError::Parse(_)))));
+		assert_eq!(output(r#"println(json.parse("[16777216, -16777216, 0.1]"));"#), "[16777216, -16777216, 0.1]\n");
+		for big in ["16777217", "-16777217", "18446744073709551615", "1e+300"] {
+			assert_eq!(
+				run(&format!(r#"json.parse("[{}]");"#, big)).err(),
+				Some(RuntimeError::Json(JsonError::Precision(big.into()))),
+			);
+		}
+	}
+
+	#[test]
+	fn stringify() {
+		assert_eq!(
+			output(r#"println(json.stringify({"b": [1, 0.1, -3, nil], "a": "x\"y"}));"#),
+			"{\"a\":\"x\\\"y\",\"b\":[1,0.1,-3,null]}\n"
+		);
+		assert_eq!(output(r#"println(json.stringify({"a": [1]}, 2));"#), "{\n  \"a\": [\n    1\n  ]\n}\n");
+		assert_eq!(
+			output(r#"println(json.stringify([1], math.pow(10, 12)));"#),
+			format!("[\n{}1\n]\n", " ".repeat(MAX_INDENT))
+		);
+		assert_eq!(output(r#"println(json.stringify(json.parse("[1.5, {{}}]")));"#), "[1.5,{}]\n");
+		assert_eq!(run("fn f() {} json.stringify([f]);").err(), Some(RuntimeError::Json(JsonError::Unserialisable("fn"))));
+		assert_eq!(run("json.stringify(math.nan);").err(), Some(RuntimeError::Json(JsonError::Unserialisable("num"))));
+	}
+}
@@ -0,0 +1,143 @@
+use std::f32::consts;
+
+use super::*;
+
+pub const MATH: Module = Module {
+	name: "math",
+	functions: &[
+		("sqrt", sqrt),
+		("pow", pow),
+		("abs", abs),
+		("floor", floor),
+		("ceil", ceil),
+		("round", round),
+		("min", min),
+		("max", max),
+		("clamp", clamp),
+		("sin", sin),
+		("cos", cos),
+		("tan", tan),
+		("asin", asin),
+		("acos", acos),
+		("atan", atan),
+		("atan2", atan2),
+		("exp", exp),
+		("log", log),
+		("log2", log2),
+		("log10", log10),
+		("is_nan", is_nan),
+		("is_finite", is_finite),
+	],
+	constants: || vec![
+		("pi", Value::VNumber(consts::PI)),
+		("e", Value::VNumber(consts::E)),
+		("inf", Value::VNumber(f32::INFINITY)),
+		("nan", Value::VNumber(f32::NAN)),
+	],
+};
+
+/// Define functions which apply an [f32] method to their one argument
+macro_rules! unary {
+	($($name:ident),*) => {$(
+		fn $name(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+			let [x] = numbers(concat!("math.", stringify!($name)), args)?;
+			Ok(Value::VNumber(x.$name()))
+		}
+	)*};
+}
+
+unary!(sqrt, abs, floor, ceil, round, sin, cos, tan, asin, acos, atan, exp, log2, log10);
+
+fn pow(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let [x, y] = numbers("math.pow", args)?;
+	Ok(Value::VNumber(x.powf(y)))
+}
+
+fn atan2(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let [y, x] = numbers("math.atan2", args)?;
+	Ok(Value::VNumber(y.atan2(x)))
+}
+
+/// The natural logarithm of `x`, or its logarithm in `base` if one is given
+fn log(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	arity("math.log", args, 1, Some(2))?;
+	let x = number("math.log", &args[0])?;
+	Ok(Value::VNumber(match args.get(1) {
+		Some(base) => x.log(number("math.log", base)?),
+		None => x.ln(),
+	}))
+}
+
+fn min(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	fold("math.min", args, f32::min)
+}
+
+fn max(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	fold("math.max", args, f32::max)
+}
+
+/// Combine one or more numbers with `f`
+fn fold(function: &str, args: &[Value], f: fn(f32, f32) -> f32) -> RuntimeResult<Value> {
+	arity(function, args, 1, None)?;
+	let first = number(function, &args[0])?;
+	args[1..]
+		.iter()
+		.try_fold(first, |acc, arg| Ok(f(acc, number(function, arg)?)))
+		.map(Value::VNumber)
+}
+
+fn clamp(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let [x, lo, hi] = numbers("math.clamp", args)?;
+	Ok(Value::VNumber(x.max(lo).min(hi)))
+}
+
+fn is_nan(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let [x] = numbers("math.is_nan", args)?;
+	Ok(Value::VBool(x.is_nan()))
+}
+
+fn is_finite(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let [x] = numbers("math.is_finite", args)?;
+	Ok(Value::VBool(x.is_finite()))
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{error::compiler::*, runtime::tests::util::*};
+	use super::*;
+
+	#[test]
+	fn functions() {
+		assert_eq!(
+			output("println(math.sqrt(16), math.pow(2, 10), math.abs(-3), math.floor(1.5), math.ceil(1.5), math.round(2.5));"),
+			"4 1024 3 1 2 3\n"
+		);
+		assert_eq!(
+			output("println(math.min(3, 1, 2), math.max(3, 1, 2), math.clamp(5, 0, 2), math.log(8, 2), math.log(1), math.exp(0));"),
+			"1 3 2 3 0 1\n"
+		);
+		assert_eq!(
+			output("println(math.is_nan(math.nan), math.is_finite(math.inf), math.cos(0), math.pi, math.e);"),
+			"true false 1 3.1415927 2.7182817\n"
+		);
+	}
+
+	#[test]
+	fn errors() {
+		assert_eq!(
+			crate::Compiler::new("math.tau;").compile(),
+			Err(CompilerError::ScopeError(ScopeError::UndefinedVariable("math.tau".into())))
+		);
+		assert_eq!(run("math.sqrt(\"4\");").err(), Some(RuntimeError::Call(CallError::ArgumentType {
+			function: "math.sqrt".into(),
+			expected: "num",
+			found: "str",
+		})));
+		assert_eq!(run("math.min();").err(), Some(RuntimeError::Call(CallError::Arity {
+			function: "math.min".into(),
+			min: 1,
+			max: None,
+			received: 0,
+		})));
+	}
+}
@@ -0,0 +1,114 @@
+use rand::{seq::SliceRandom, Rng};
+
+use super::*;
+
+/// Random numbers, from the generator owned by the runtime. Seeding it with [Runtime::seed] makes the
+/// numbers the same on every run.
+pub const RANDOM: Module = Module {
+	name: "random",
+	functions: &[
+		("int", int),
+		("float", float),
+		("choice", choice),
+		("shuffle", shuffle),
+	],
+	constants: Vec::new,
+};
+
+/// Get the list passed as an argument to a native
+fn list<'v>(function: &str, arg: &'v Value) -> RuntimeResult<&'v Vec<Value>> {
+	match arg {
+		Value::VList(items) => Ok(items),
+		v => Err(RuntimeError::Call(CallError::ArgumentType {
+			function: function.into(),
+			expected: "list",
+			found: v.type_name(),
+		})),
+	}
+}
+
+/// A whole number from `lo` up to and including `hi`
+fn int(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let [lo, hi] = numbers("random.int", args)?;
+	let (lo, hi) = (lo.ceil() as i64, hi.floor() as i64);
+	if lo > hi {
+		return Err(RuntimeError::Call(CallError::InvalidArgument {
+			function: "random.int".into(),
+			reason: "there are no whole numbers between the bounds",
+		}));
+	}
+	Ok(Value::VNumber(runtime.rng.gen_range(lo..=hi) as f32))
+}
+
+/// A number from 0 up to (but not including) 1
+fn float(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	arity("random.float", args, 0, Some(0))?;
+	Ok(Value::VNumber(runtime.rng.gen::<f32>()))
+}
+
+/// A random item from a list, or nil if it is empty
+fn choice(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	arity("random.choice", args, 1, Some(1))?;
+	let items = list("random.choice", &args[0])?;
+	Ok(items.choose(&mut runtime.rng).cloned().unwrap_or(Value::VNil))
+}
+
+/// A copy of a list with its items in a random order
+fn shuffle(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	arity("random.shuffle", args, 1, Some(1))?;
+	runtime.reserve(args[0].heap_size())?;
+	let mut items = list("random.shuffle", &args[0])?.clone();
+	items.shuffle(&mut runtime.rng);
+	Ok(Value::VList(items))
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::runtime::tests::util::*;
+	use super::*;
+
+	fn seeded(source: &str, seed: u64) -> Runtime {
+		let mut runtime = load(source);
+		runtime.seed(seed);
+		runtime.exec().unwrap();
+		runtime
+	}
+
+	#[test]
+	fn reproducible() {
+		let source = r#"
+			let mut rolls = "";
+			for _ in 0..20 { rolls = "{rolls}{random.int(1, 6)}"; }
+			let f = random.float();
+			let c = random.choice(["a", "b", "c"]);
+			let s = random.shuffle([1, 2, 3, 4, 5]);
+		"#;
+		let (a, b, c) = (seeded(source, 42), seeded(source, 42), seeded(source, 7));
+		for name in ["rolls", "f", "c", "s"] {
+			assert_eq!(var(&a, name), var(&b, name));
+		}
+		assert_ne!(var(&a, "rolls"), var(&c, "rolls"));
+
+		match var(&a, "rolls") {
+			Value::VString(rolls) => assert!(rolls.len() == 20 && rolls.chars().all(|r| ('1'..='6').contains(&r))),
+			v => panic!("expected a string, found {}", v),
+		}
+		match var(&a, "s") {
+			Value::VList(items) => {
+				let mut sorted: Vec<String> = items.iter().map(|i| i.to_string()).collect();
+				sorted.sort();
+				assert_eq!(sorted, ["1", "2", "3", "4", "5"]);
+			}
+			v => panic!("expected a list, found {}", v),
+		}
+	}
+
+	#[test]
+	fn errors() {
+		assert_eq!(run("random.choice([]);").map(|_| ()), Ok(()));
+		assert_eq!(run("random.int(3, 1);").err(), Some(RuntimeError::Call(CallError::InvalidArgument {
+			function: "random.int".into(),
+			reason: "there are no whole numbers between the bounds",
+		})));
+	}
+}
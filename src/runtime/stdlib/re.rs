@@ -0,0 +1,191 @@
+use std::fmt;
+
+use regex::Regex;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::*;
+
+/// Regular expressions. Functions take either a pattern compiled with `re.compile`, which can be kept in a
+/// variable and reused, or a string which is compiled on every call.
+pub const RE: Module = Module {
+	name: "re",
+	functions: &[
+		("compile", compile),
+		("match", matches),
+		("captures", captures),
+		("find_all", find_all),
+		("replace", replace),
+		("split", split),
+	],
+	constants: Vec::new,
+};
+
+/// A compiled regular expression, which is compared and serialized by its source
+#[derive(Clone, Debug)]
+pub struct Pattern(pub Regex);
+
+impl Pattern {
+	pub fn new(pattern: &str) -> RuntimeResult<Self> {
+		let invalid = |position, message: String| RuntimeError::Regex(RegexError::Invalid {
+			pattern: pattern.into(),
+			position,
+			message,
+		});
+		// The regex crate only reports where a syntax error is in its message, so parse the pattern
+		// separately to find the position
+		if let Err(e) = regex_syntax::Parser::new().parse(pattern) {
+			let (offset, message) = match &e {
+				regex_syntax::Error::Parse(e) => (e.span().start.offset, e.kind().to_string()),
+				regex_syntax::Error::Translate(e) => (e.span().start.offset, e.kind().to_string()),
+				e => (0, e.to_string()),
+			};
+			return Err(invalid(Some(pattern[..offset].chars().count()), message));
+		}
+		Regex::new(pattern)
+			.map(Self)
+			.map_err(|e| invalid(None, e.to_string()))
+	}
+}
+
+impl PartialEq for Pattern {
+	fn eq(&self, other: &Self) -> bool {
+		self.0.as_str() == other.0.as_str()
+	}
+}
+
+impl fmt::Display for Pattern {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "/{}/", self.0.as_str())
+	}
+}
+
+impl Serialize for Pattern {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(self.0.as_str())
+	}
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let pattern = String::deserialize(deserializer)?;
+		Regex::new(&pattern).map(Self).map_err(de::Error::custom)
+	}
+}
+
+/// Get the pattern passed as an argument, compiling it if it is a string
+fn pattern(function: &str, arg: &Value) -> RuntimeResult<Regex> {
+	match arg {
+		Value::VRegex(pattern) => Ok(pattern.0.clone()),
+		Value::VString(pattern) => Pattern::new(pattern).map(|p| p.0),
+		v => Err(RuntimeError::Call(CallError::ArgumentType {
+			function: function.into(),
+			expected: "regex",
+			found: v.type_name(),
+		})),
+	}
+}
+
+/// Get the pattern and text that most functions take
+fn pattern_and_text<'v>(function: &str, args: &'v [Value], extra: usize) -> RuntimeResult<(Regex, &'v str)> {
+	arity(function, args, 2 + extra, Some(2 + extra))?;
+	Ok((pattern(function, &args[0])?, string(function, &args[1])?))
+}
+
+fn group(m: Option<regex::Match>) -> Value {
+	m.map(|m| Value::VString(m.as_str().into())).unwrap_or(Value::VNil)
+}
+
+fn compile(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	arity("re.compile", args, 1, Some(1))?;
+	Ok(Value::VRegex(Box::new(Pattern::new(string("re.compile", &args[0])?)?)))
+}
+
+/// The first match in the text as a list of its groups, starting with the whole match, or nil if there
+/// isn't one. Groups that didn't take part in the match are nil.
+fn matches(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let (re, text) = pattern_and_text("re.match", args, 0)?;
+	Ok(match re.captures(text) {
+		Some(caps) => Value::VList(caps.iter().map(group).collect()),
+		None => Value::VNil,
+	})
+}
+
+/// The named groups of the first match in the text as a map, or nil if there isn't a match
+fn captures(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let (re, text) = pattern_and_text("re.captures", args, 0)?;
+	Ok(match re.captures(text) {
+		Some(caps) => Value::VMap(
+			re.capture_names()
+				.flatten()
+				.map(|name| (name.to_string(), group(caps.name(name))))
+				.collect(),
+		),
+		None => Value::VNil,
+	})
+}
+
+/// Every match in the text
+fn find_all(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let (re, text) = pattern_and_text("re.find_all", args, 0)?;
+	Ok(Value::VList(re.find_iter(text).map(|m| group(Some(m))).collect()))
+}
+
+/// Replace every match in the text. The replacement can refer to groups as `$1` or `$name`.
+fn replace(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let (re, text) = pattern_and_text("re.replace", args, 1)?;
+	let replacement = string("re.replace", &args[2])?;
+	// Work out how long the result will be before building it
+	let mut len = text.len();
+	let mut expanded = String::new();
+	for caps in re.captures_iter(text) {
+		expanded.clear();
+		caps.expand(replacement, &mut expanded);
+		len = (len - caps[0].len()).saturating_add(expanded.len());
+	}
+	runtime.reserve(len)?;
+	Ok(Value::VString(re.replace_all(text, replacement).into_owned()))
+}
+
+fn split(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let (re, text) = pattern_and_text("re.split", args, 0)?;
+	Ok(Value::VList(re.split(text).map(|s| Value::VString(s.into())).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::runtime::tests::util::*;
+	use super::*;
+
+	#[test]
+	fn functions() {
+		let runtime = run(r#"
+			let line = re.compile("(?P<level>[A-Z]+) (?P<code>\\d+)?");
+			let first = re.match(line, "INFO 200 ok, WARN later");
+			let named = re.captures(line, "WARN later");
+			let missing = re.match(line, "nothing here");
+			let all = re.find_all("\\d+", "a1b22c333");
+			let replaced = re.replace("(\\w+)@(\\w+)", "bob@home, amy@work", "$2:$1");
+			let parts = re.split("[,;]\\s*", "a, b;c");
+		"#).unwrap();
+		assert_eq!(var(&runtime, "first").to_string(), r#"["INFO 200", "INFO", "200"]"#);
+		assert_eq!(var(&runtime, "named").to_string(), r#"{"code": nil, "level": "WARN"}"#);
+		assert_eq!(var(&runtime, "missing"), &Value::VNil);
+		assert_eq!(var(&runtime, "all").to_string(), r#"["1", "22", "333"]"#);
+		assert_eq!(var(&runtime, "replaced").to_string(), "home:bob, work:amy");
+		assert_eq!(var(&runtime, "parts").to_string(), r#"["a", "b", "c"]"#);
+		assert_eq!(var(&runtime, "line").to_string(), r"/(?P<level>[A-Z]+) (?P<code>\d+)?/");
+	}
+
+	#[test]
+	fn invalid() {
+		assert_eq!(run(r#"re.compile("ab(c");"#).err(), Some(RuntimeError::Regex(RegexError::Invalid {
+			pattern: "ab(c".into(),
+			position: Some(2),
+			message: "unclosed group".into(),
+		})));
+		assert!(matches!(
+			run(r#"re.find_all("é[", "");"#).err(),
+			Some(RuntimeError::Regex(RegexError::Invalid { position: Some(1), .. }))
+		));
+	}
+}
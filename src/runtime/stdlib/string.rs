@@ -0,0 +1,231 @@
+use super::*;
+
+/// String functions, which are also the methods of strings.
+/// Positions in strings count characters rather than bytes.
+pub const STR: Module = Module {
+	name: "str",
+	functions: &[
+		("len", len),
+		("upper", upper),
+		("lower", lower),
+		("trim", trim),
+		("split", split),
+		("join", join),
+		("replace", replace),
+		("contains", contains),
+		("starts_with", starts_with),
+		("find", find),
+		("slice", slice),
+		("repeat", repeat),
+		("parse_number", parse_number),
+		("to_string", to_string),
+	],
+	constants: Vec::new,
+};
+
+/// Get the string a function was called on, and check that it was given `extra` more arguments
+fn receiver<'v>(function: &str, args: &'v [Value], extra: usize) -> RuntimeResult<&'v str> {
+	arity(function, args, 1 + extra, Some(1 + extra))?;
+	string(function, &args[0])
+}
+
+fn len(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let s = receiver("str.len", args, 0)?;
+	Ok(Value::VNumber(s.chars().count() as f32))
+}
+
+fn upper(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let s = receiver("str.upper", args, 0)?;
+	runtime.reserve(s.len())?;
+	Ok(Value::VString(s.to_uppercase()))
+}
+
+fn lower(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let s = receiver("str.lower", args, 0)?;
+	runtime.reserve(s.len())?;
+	Ok(Value::VString(s.to_lowercase()))
+}
+
+fn trim(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let s = receiver("str.trim", args, 0)?;
+	Ok(Value::VString(s.trim().into()))
+}
+
+/// Split on `separator`, or on whitespace if there isn't one
+fn split(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	arity("str.split", args, 1, Some(2))?;
+	let s = string("str.split", &args[0])?;
+	let parts: Vec<Value> = match args.get(1) {
+		Some(separator) => s
+			.split(string("str.split", separator)?)
+			.map(|p| Value::VString(p.into()))
+			.collect(),
+		None => s.split_whitespace().map(|p| Value::VString(p.into())).collect(),
+	};
+	Ok(Value::VList(parts))
+}
+
+/// Join the items of a list with the string as the separator, i.e. `", ".join(items)`
+fn join(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let separator = receiver("str.join", args, 1)?;
+	match &args[1] {
+		Value::VList(items) => {
+			let separators = separator.len().saturating_mul(items.len().saturating_sub(1));
+			runtime.reserve(items.iter().map(Value::heap_size).sum::<usize>().saturating_add(separators))?;
+			Ok(Value::VString(items.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(separator)))
+		}
+		v => Err(RuntimeError::Call(CallError::ArgumentType {
+			function: "str.join".into(),
+			expected: "list",
+			found: v.type_name(),
+		})),
+	}
+}
+
+fn replace(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let s = receiver("str.replace", args, 2)?;
+	let from = string("str.replace", &args[1])?;
+	let to = string("str.replace", &args[2])?;
+	let replaced = s.matches(from).count();
+	let len = (s.len() - replaced * from.len()).saturating_add(replaced.saturating_mul(to.len()));
+	runtime.reserve(len)?;
+	Ok(Value::VString(s.replace(from, to)))
+}
+
+fn contains(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let s = receiver("str.contains", args, 1)?;
+	Ok(Value::VBool(s.contains(string("str.contains", &args[1])?)))
+}
+
+fn starts_with(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let s = receiver("str.starts_with", args, 1)?;
+	Ok(Value::VBool(s.starts_with(string("str.starts_with", &args[1])?)))
+}
+
+/// The position of the first occurrence of `needle`, or nil if there isn't one
+fn find(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let s = receiver("str.find", args, 1)?;
+	let needle = string("str.find", &args[1])?;
+	Ok(match s.find(needle) {
+		Some(byte) => Value::VNumber(s[..byte].chars().count() as f32),
+		None => Value::VNil,
+	})
+}
+
+/// The characters from `start` up to (but not including) `end`, or the end of the string
+fn slice(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	arity("str.slice", args, 2, Some(3))?;
+	let s = string("str.slice", &args[0])?;
+	let start = index("str.slice", &args[1])?;
+	let end = match args.get(2) {
+		Some(end) => index("str.slice", end)?,
+		None => usize::MAX,
+	};
+	let taken = end.saturating_sub(start);
+	Ok(Value::VString(s.chars().skip(start).take(taken).collect()))
+}
+
+fn repeat(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let s = receiver("str.repeat", args, 1)?;
+	let times = index("str.repeat", &args[1])?;
+	let too_long = || RuntimeError::Call(CallError::InvalidArgument {
+		function: "str.repeat".into(),
+		reason: "the result is too long",
+	});
+	let len = s.len().checked_mul(times).ok_or_else(too_long)?;
+	runtime.reserve(len)?;
+	let mut repeated = String::new();
+	repeated.try_reserve_exact(len).map_err(|_| too_long())?;
+	for _ in 0..times {
+		repeated.push_str(s);
+	}
+	Ok(Value::VString(repeated))
+}
+
+/// The number written in the string, or nil if it isn't one
+fn parse_number(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let s = receiver("str.parse_number", args, 0)?;
+	Ok(match s.trim().parse::<f32>() {
+		Ok(n) => Value::VNumber(n),
+		Err(_) => Value::VNil,
+	})
+}
+
+/// Format any value the way `print` would
+fn to_string(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	arity("str.to_string", args, 1, Some(1))?;
+	runtime.reserve(args[0].heap_size())?;
+	Ok(Value::VString(args[0].to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::runtime::tests::util::*;
+	use super::*;
+
+	#[test]
+	fn methods() {
+		assert_eq!(
+			output(r#"let s = "  Héllo World "; println(s.len(), s.trim().upper(), s.trim().lower(), s.trim().len());"#),
+			"14 HÉLLO WORLD héllo world 11\n"
+		);
+		assert_eq!(
+			output(r#"println("a,b,,c".split(","), " a  b ".split(), "-".join(["x", 1, true]));"#),
+			"[\"a\", \"b\", \"\", \"c\"] [\"a\", \"b\"] x-1-true\n"
+		);
+		assert_eq!(
+			output(r#"let s = "héllo"; println(s.replace("l", "L"), s.contains("él"), s.starts_with("hé"), s.find("l"), s.find("z"));"#),
+			"héLLo true true 2 nil\n"
+		);
+		assert_eq!(
+			output(r#"let s = "héllo"; println(s.slice(1, 3), s.slice(3), s.slice(4, 99), "ab".repeat(3));"#),
+			"él lo o ababab\n"
+		);
+		assert_eq!(
+			output(r#"println(" 1.5 ".parse_number() + 1, "x".parse_number(), str.to_string([1, "a"]).len(), "a".to_string());"#),
+			"2.5 nil 8 a\n"
+		);
+	}
+
+	#[test]
+	fn methods_in_expressions() {
+		let runtime = run(r#"
+			let mut letters = 0;
+			for w in "the quick fox".split(" ") { letters = letters + w.len(); }
+			let shout = "{"hi".upper()}!";
+			fn greet(name) { return "hello {name}".upper(); }
+			let greeting = greet("bob".slice(0, 1).upper());
+		"#).unwrap();
+		assert_eq!(var(&runtime, "letters"), &Value::VNumber(11.));
+		assert_eq!(var(&runtime, "shout"), &Value::VString("HI!".into()));
+		assert_eq!(var(&runtime, "greeting"), &Value::VString("HELLO B".into()));
+	}
+
+	#[test]
+	fn errors() {
+		assert_eq!(run("let n = 1; n.upper();").err(), Some(RuntimeError::Call(CallError::NoMethod {
+			ty: "num",
+			name: "upper".into(),
+		})));
+		assert_eq!(run(r#""a".repeat(-1);"#).err(), Some(RuntimeError::Call(CallError::ArgumentType {
+			function: "str.repeat".into(),
+			expected: "index",
+			found: "num",
+		})));
+		assert_eq!(run(r#""a".repeat(math.pow(10, 30));"#).err(), Some(RuntimeError::Call(CallError::ArgumentType {
+			function: "str.repeat".into(),
+			expected: "index",
+			found: "num",
+		})));
+		assert_eq!(run(r#""ab".repeat(math.pow(10, 19));"#).err(), Some(RuntimeError::Call(CallError::InvalidArgument {
+			function: "str.repeat".into(),
+			reason: "the result is too long",
+		})));
+		assert_eq!(run(r#""a".contains();"#).err(), Some(RuntimeError::Call(CallError::Arity {
+			function: "str.contains".into(),
+			min: 2,
+			max: Some(2),
+			received: 1,
+		})));
+	}
+}
@@ -0,0 +1,209 @@
+use std::{cell::Cell, time::{Duration, Instant}};
+
+use chrono::{format::{Item, StrftimeItems}, DateTime, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+
+use super::*;
+
+/// Time functions. Numbers can't hold a timestamp precisely, so dates and times are written as RFC 3339
+/// strings in UTC (i.e. `2022-07-04T12:30:00.000Z`), while durations are numbers.
+pub const TIME: Module = Module {
+	name: "time",
+	functions: &[
+		("now", now),
+		("monotonic", monotonic),
+		("sleep", sleep),
+		("format", format),
+		("parse", parse),
+	],
+	constants: Vec::new,
+};
+
+/// Where the `time` module gets the time from. Hosts can give the runtime their own clock, i.e. to make
+/// tests deterministic.
+pub trait Clock: Send {
+	/// The current date and time
+	fn now(&self) -> DateTime<Utc>;
+	/// The time since some fixed point, which never goes backwards
+	fn monotonic(&self) -> Duration;
+	fn sleep(&self, duration: Duration);
+}
+
+/// The system's clock
+pub struct SystemClock {
+	start: Instant,
+}
+
+impl Default for SystemClock {
+	fn default() -> Self {
+		Self { start: Instant::now() }
+	}
+}
+
+impl Clock for SystemClock {
+	fn now(&self) -> DateTime<Utc> {
+		Utc::now()
+	}
+
+	fn monotonic(&self) -> Duration {
+		self.start.elapsed()
+	}
+
+	fn sleep(&self, duration: Duration) {
+		std::thread::sleep(duration)
+	}
+}
+
+/// A clock which only moves when it is told to, or when a script sleeps
+pub struct ManualClock {
+	now: Cell<DateTime<Utc>>,
+	elapsed: Cell<Duration>,
+}
+
+impl ManualClock {
+	pub fn new(now: DateTime<Utc>) -> Self {
+		Self {
+			now: Cell::new(now),
+			elapsed: Cell::new(Duration::ZERO),
+		}
+	}
+
+	pub fn advance(&self, duration: Duration) {
+		self.now.set(self.now.get() + duration);
+		self.elapsed.set(self.elapsed.get() + duration);
+	}
+}
+
+impl Clock for ManualClock {
+	fn now(&self) -> DateTime<Utc> {
+		self.now.get()
+	}
+
+	fn monotonic(&self) -> Duration {
+		self.elapsed.get()
+	}
+
+	fn sleep(&self, duration: Duration) {
+		self.advance(duration)
+	}
+}
+
+fn timestamp(time: DateTime<Utc>) -> Value {
+	Value::VString(time.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+/// Read a timestamp written by [timestamp]
+fn read_timestamp(function: &str, arg: &Value) -> RuntimeResult<DateTime<Utc>> {
+	let text = string(function, arg)?;
+	DateTime::parse_from_rfc3339(text)
+		.map(|t| t.with_timezone(&Utc))
+		.map_err(|e| RuntimeError::Time(TimeError::Parse(format!("{}: {}", text, e))))
+}
+
+/// The current time
+fn now(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	arity("time.now", args, 0, Some(0))?;
+	Ok(timestamp(runtime.clock.now()))
+}
+
+/// Seconds since the clock started, for measuring how long something took
+fn monotonic(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	arity("time.monotonic", args, 0, Some(0))?;
+	Ok(Value::VNumber(runtime.clock.monotonic().as_secs_f32()))
+}
+
+/// Pause for a number of milliseconds
+fn sleep(runtime: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	let [ms] = numbers("time.sleep", args)?;
+	let duration = match ms.is_nan() {
+		true => None,
+		false => Duration::try_from_secs_f32(ms.max(0.) / 1000.).ok(),
+	};
+	let duration = duration.ok_or_else(|| RuntimeError::Call(CallError::InvalidArgument {
+		function: "time.sleep".into(),
+		reason: "the duration is out of range",
+	}))?;
+	runtime.clock.sleep(duration);
+	Ok(Value::VNil)
+}
+
+/// Format a timestamp with a strftime style format, i.e. `time.format(t, "%Y-%m-%d")`
+fn format(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	arity("time.format", args, 2, Some(2))?;
+	let time = read_timestamp("time.format", &args[0])?;
+	let format = string("time.format", &args[1])?;
+	// Formatting panics on invalid formats, so check first
+	let items: Vec<Item> = StrftimeItems::new(format).collect();
+	if items.contains(&Item::Error) {
+		return Err(RuntimeError::Time(TimeError::Format(format.into())));
+	}
+	Ok(Value::VString(time.format_with_items(items.into_iter()).to_string()))
+}
+
+/// Parse a date and time written in `format`, or in RFC 3339 if there isn't one.
+/// Times without an offset are taken to be in UTC, and dates without a time to be at midnight.
+fn parse(_: &mut Runtime, args: &[Value]) -> RuntimeResult<Value> {
+	arity("time.parse", args, 1, Some(2))?;
+	let format = match args.get(1) {
+		Some(format) => string("time.parse", format)?,
+		None => return read_timestamp("time.parse", &args[0]).map(timestamp),
+	};
+	let text = string("time.parse", &args[0])?;
+	let time = DateTime::parse_from_str(text, format)
+		.map(|t| t.with_timezone(&Utc))
+		.or_else(|_| NaiveDateTime::parse_from_str(text, format).map(|t| t.and_utc()))
+		.or_else(|_| NaiveDate::parse_from_str(text, format).map(|d| d.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc()))
+		.map_err(|e| RuntimeError::Time(TimeError::Parse(format!("{}: {}", text, e))))?;
+	Ok(timestamp(time))
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::runtime::tests::util::*;
+	use super::*;
+
+	fn manual(source: &str) -> String {
+		let mut runtime = load(source);
+		let start = DateTime::parse_from_rfc3339("2022-07-04T12:30:00Z").unwrap().with_timezone(&Utc);
+		runtime.set_clock(ManualClock::new(start));
+		let output = Output::default();
+		runtime.set_output(output.clone());
+		runtime.exec().unwrap();
+		let printed = output.0.lock().unwrap().clone();
+		String::from_utf8(printed).unwrap()
+	}
+
+	#[test]
+	fn clock() {
+		assert_eq!(
+			manual("println(time.now(), time.monotonic()); time.sleep(1500); println(time.now(), time.monotonic());"),
+			"2022-07-04T12:30:00.000Z 0\n2022-07-04T12:30:01.500Z 1.5\n"
+		);
+		assert_eq!(manual("time.sleep(-5); println(time.monotonic());"), "0\n");
+		for ms in ["math.inf", "math.nan", "math.pow(10, 30)"] {
+			assert_eq!(
+				run(&format!("time.sleep({});", ms)).err(),
+				Some(RuntimeError::Call(CallError::InvalidArgument {
+					function: "time.sleep".into(),
+					reason: "the duration is out of range",
+				}))
+			);
+		}
+	}
+
+	#[test]
+	fn format_and_parse() {
+		assert_eq!(
+			manual(r#"println(time.format(time.now(), "%Y/%m/%d %H:%M"), time.format(time.now(), "%A"));"#),
+			"2022/07/04 12:30 Monday\n"
+		);
+		assert_eq!(
+			output(r#"println(time.parse("2022-07-04T14:30:00+02:00"), time.parse("04/07/2022", "%d/%m/%Y"), time.parse("2022-07-04 08:15:30", "%Y-%m-%d %H:%M:%S"));"#),
+			"2022-07-04T12:30:00.000Z 2022-07-04T00:00:00.000Z 2022-07-04T08:15:30.000Z\n"
+		);
+		assert!(matches!(run(r#"time.parse("yesterday");"#).err(), Some(RuntimeError::Time(TimeError::Parse(_)))));
+		assert_eq!(
+			run(r#"time.format(time.now(), "%Q");"#).err(),
+			Some(RuntimeError::Time(TimeError::Format("%Q".into())))
+		);
+	}
+}
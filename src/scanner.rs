@@ -1,4 +1,4 @@
-use logos::Logos;
+use logos::{Lexer, Logos};
 #[derive(PartialEq, Debug, Clone, PartialOrd, Logos)]
 #[allow(unused)]
 pub enum TokenKind {
@@ -19,6 +19,8 @@ pub enum TokenKind {
     Comma,
     #[token(".")]
     Dot,
+    #[token(":")]
+    Colon,
     #[token("-")]
     Minus,
     #[token("+")]
@@ -49,12 +51,20 @@ pub enum TokenKind {
     Less,
     #[token("<=")]
     LessEqual,
+    #[token("..")]
+    DotDot,
+    #[token("...")]
+    Ellipsis,
+    #[token("->")]
+    Arrow,
 
     // Literals.
     #[regex("[a-zA-Z_]+[a-zA-Z_0-9]*")]
     Identifier,
-    #[regex(r"-?([0-9]+([.][0-9]*)?|[.][0-9]+)", |lex| lex.slice().parse::<f32>().unwrap())]
+    #[regex(r"-?[0-9]+([.][0-9]+)?", |lex| lex.slice().parse::<f32>().unwrap())]
     Number(f32),
+    #[token("\"", string)]
+    Str,
 
     // Keywords.
     #[token("&&")]
@@ -75,6 +85,8 @@ pub enum TokenKind {
     Fn,
     #[token("for")]
     For,
+    #[token("in")]
+    In,
     #[token("nil")]
     Nil,
     #[token("return")]
@@ -83,14 +95,112 @@ pub enum TokenKind {
     This,
     #[token("let")]
     Let,
+    #[token("mut")]
+    Mut,
+    #[token("const")]
+    Const,
     #[token("while")]
     While,
-    #[token("\n")]
-    Newline,
 
     #[error]
-    #[regex(r"[ \t\f]+", logos::skip)]
-    #[regex(r"//.**\n", logos::skip)]
+    #[regex(r"[ \t\f\r\n]+", logos::skip)]
+    #[regex(r"//[^\n]*", logos::skip)]
     #[regex(r"/\*(.|\n)*\*/\n", logos::skip)]
     Error,
 }
+
+/// A piece of a string literal
+#[derive(PartialEq, Debug, Clone, PartialOrd)]
+pub enum StrPart {
+    Text(String),
+    /// The start and end of an interpolated expression's source
+    Expr(usize, usize),
+}
+
+/// Lex a string literal after its opening quote.
+/// The literal is scanned in text mode until a `{` switches to expression mode, which lasts until the
+/// matching `}`. `{{` and `}}` are escapes for literal braces.
+/// The parts aren't stored in the token, as [Lexer]s can't be cloned safely while holding tokens
+/// that own heap data. Use [string_parts] on the token's slice instead.
+fn string(lex: &mut Lexer<TokenKind>) -> bool {
+    match string_parts(lex.remainder(), 0) {
+        Some((_, len)) => {
+            lex.bump(len);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Split the string literal at the start of `src` (after the opening quote) into parts, also
+/// returning the length of the literal including its closing quote. `offset` is the position of
+/// `src` in the source.
+pub fn string_parts(src: &str, offset: usize) -> Option<(Vec<StrPart>, usize)> {
+    let bytes = src.as_bytes();
+    let mut parts = vec![];
+    let mut text = String::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                if !text.is_empty() || parts.is_empty() {
+                    parts.push(StrPart::Text(text));
+                }
+                return Some((parts, i + 1));
+            }
+            b'\\' => {
+                let c = src[i + 1..].chars().next()?;
+                text.push(match c {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    '0' => '\0',
+                    c => c,
+                });
+                i += 1 + c.len_utf8();
+            }
+            b'{' if bytes.get(i + 1) == Some(&b'{') => {
+                text.push('{');
+                i += 2;
+            }
+            b'}' if bytes.get(i + 1) == Some(&b'}') => {
+                text.push('}');
+                i += 2;
+            }
+            // A lone closing brace is most likely a typo
+            b'}' => return None,
+            b'{' => {
+                let len = expression_len(&src[i + 1..])?;
+                if !text.is_empty() {
+                    parts.push(StrPart::Text(std::mem::take(&mut text)));
+                }
+                parts.push(StrPart::Expr(offset + i + 1, offset + i + 1 + len));
+                i += len + 2;
+            }
+            _ => {
+                let c = src[i..].chars().next()?;
+                text.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    None
+}
+
+/// Find the length of the interpolated expression at the start of `src`, up to its closing `}`
+fn expression_len(src: &str) -> Option<usize> {
+    let bytes = src.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' => depth += 1,
+            b'}' if depth == 0 => return Some(i),
+            b'}' => depth -= 1,
+            b'"' => i += string_parts(&src[i + 1..], 0)?.1,
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
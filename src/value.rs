@@ -1,9 +1,121 @@
+use std::{collections::BTreeMap, fmt};
+
 use serde::{Deserialize, Serialize};
+
+use crate::runtime::{Function, Host, Iter, stdlib::Pattern};
+
+mod de;
+mod ser;
+pub use de::from_value;
+pub use ser::{to_value, Serializer};
+
 #[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
 pub enum Value {
     VString(String),
     VNumber(f32),
     VBool(bool),
+    VNil,
+    VList(Vec<Value>),
+    VMap(BTreeMap<String, Value>),
+    VRange(f32, f32),
+    VIter(Box<Iter>),
+    VFunction(Box<Function>),
+    /// A native function, by the name it was registered under
+    VNative(String),
+    /// A compiled regular expression
+    VRegex(Box<Pattern>),
+    /// An object owned by the host
+    VHost(Host),
+}
+
+impl Value {
+    /// The name of the value's type as it is spelled in blush code
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::VString(_) => "str",
+            Self::VNumber(_) => "num",
+            Self::VBool(_) => "bool",
+            Self::VNil => "nil",
+            Self::VList(_) => "list",
+            Self::VMap(_) => "map",
+            Self::VRange(..) => "range",
+            Self::VIter(_) => "iter",
+            Self::VFunction(_) | Self::VNative(_) => "fn",
+            Self::VRegex(_) => "regex",
+            Self::VHost(host) => host.0.type_name(),
+        }
+    }
+
+    /// Roughly how many bytes the value owns on the heap, for enforcing memory limits.
+    /// Host objects are owned by the host, so they aren't counted.
+    pub fn heap_size(&self) -> usize {
+        use crate::runtime::Iter;
+        const VALUE: usize = std::mem::size_of::<Value>();
+        match self {
+            Self::VString(s) | Self::VNative(s) => s.len(),
+            Self::VList(items) => items.iter().map(|v| VALUE + v.heap_size()).sum(),
+            Self::VMap(map) => map.iter().map(|(k, v)| k.len() + VALUE + v.heap_size()).sum(),
+            Self::VIter(iter) => std::mem::size_of::<Iter>() + match iter.as_ref() {
+                Iter::List(items, _) => items.iter().map(|v| VALUE + v.heap_size()).sum(),
+                Iter::Keys(keys, _) => keys.iter().map(|k| k.len() + std::mem::size_of::<String>()).sum(),
+                Iter::Chars(chars, _) => chars.len() * std::mem::size_of::<char>(),
+                Iter::Range(..) => 0,
+                Iter::Object(object) => object.heap_size(),
+            },
+            Self::VFunction(f) => std::mem::size_of_val(f.as_ref()) + f.name.len()
+                + f.params.iter().map(|p| std::mem::size_of_val(p) + p.name.len()).sum::<usize>(),
+            Self::VRegex(pattern) => pattern.0.as_str().len(),
+            Self::VNumber(_) | Self::VBool(_) | Self::VNil | Self::VRange(..) | Self::VHost(_) => 0,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    /// Format the value the way it would be printed by a script.
+    /// Strings are written as is at the top level, but quoted inside collections.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        /// Format an item inside of a collection
+        fn item(f: &mut fmt::Formatter<'_>, value: &Value) -> fmt::Result {
+            match value {
+                Value::VString(s) => write!(f, "{:?}", s),
+                v => write!(f, "{}", v),
+            }
+        }
+
+        match self {
+            Self::VString(s) => write!(f, "{}", s),
+            Self::VNumber(n) => write!(f, "{}", n),
+            Self::VBool(b) => write!(f, "{}", b),
+            Self::VNil => write!(f, "nil"),
+            Self::VList(items) => {
+                write!(f, "[")?;
+                for (i, v) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item(f, v)?;
+                }
+                write!(f, "]")
+            }
+            Self::VMap(map) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in map.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}: ", k)?;
+                    item(f, v)?;
+                }
+                write!(f, "}}")
+            }
+            Self::VRange(start, end) => write!(f, "{}..{}", start, end),
+            Self::VIter(_) => write!(f, "<iter>"),
+            Self::VFunction(func) => write!(f, "<fn {}>", func.name),
+            Self::VNative(name) => write!(f, "<native fn {}>", name),
+            Self::VRegex(pattern) => write!(f, "{}", pattern),
+            Self::VHost(host) => write!(f, "<{}>", host.0.type_name()),
+        }
+    }
 }
 
 impl Default for Value {
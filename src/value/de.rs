@@ -0,0 +1,277 @@
+use serde::{
+	de::{self, value::{MapDeserializer, SeqDeserializer}, DeserializeOwned, IntoDeserializer, Visitor},
+	forward_to_deserialize_any,
+};
+
+use crate::{Value, error::value::*};
+
+/// Convert a blush value into any Rust type that can be deserialized, using the same shapes as [super::to_value]
+pub fn from_value<T: DeserializeOwned>(value: Value) -> ValueResult<T> {
+	T::deserialize(value)
+}
+
+impl<'de> IntoDeserializer<'de, ValueError> for Value {
+	type Deserializer = Self;
+
+	fn into_deserializer(self) -> Self {
+		self
+	}
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+	type Error = ValueError;
+
+	fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> ValueResult<V::Value> {
+		match self {
+			Value::VNil => visitor.visit_unit(),
+			Value::VBool(b) => visitor.visit_bool(b),
+			// Whole numbers are given as integers so that they can be read into integer types
+			Value::VNumber(n) if n.fract() == 0. && n.abs() < i64::MAX as f32 => visitor.visit_i64(n as i64),
+			Value::VNumber(n) => visitor.visit_f32(n),
+			Value::VString(s) => visitor.visit_string(s),
+			Value::VList(items) => visitor.visit_seq(SeqDeserializer::new(items.into_iter())),
+			Value::VMap(map) => visitor.visit_map(MapDeserializer::new(map.into_iter().map(|(k, v)| (Key(k), v)))),
+			v => Err(ValueError::Unsupported(v.type_name())),
+		}
+	}
+
+	fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> ValueResult<V::Value> {
+		match self {
+			Value::VNil => visitor.visit_none(),
+			v => visitor.visit_some(v),
+		}
+	}
+
+	fn deserialize_newtype_struct<V: Visitor<'de>>(self, _: &'static str, visitor: V) -> ValueResult<V::Value> {
+		visitor.visit_newtype_struct(self)
+	}
+
+	fn deserialize_enum<V: Visitor<'de>>(
+		self,
+		_: &'static str,
+		_: &'static [&'static str],
+		visitor: V,
+	) -> ValueResult<V::Value> {
+		match self {
+			Value::VString(variant) => visitor.visit_enum(Enum { variant, value: None }),
+			Value::VMap(map) if map.len() == 1 => {
+				let (variant, value) = map.into_iter().next().unwrap_or_default();
+				visitor.visit_enum(Enum { variant, value: Some(value) })
+			}
+			v => Err(de::Error::invalid_type(v.unexpected(), &"a string or a map with one entry")),
+		}
+	}
+
+	forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+	}
+}
+
+impl Value {
+	/// Describe the value for serde's error messages
+	fn unexpected(&self) -> de::Unexpected<'_> {
+		match self {
+			Value::VNil => de::Unexpected::Unit,
+			Value::VBool(b) => de::Unexpected::Bool(*b),
+			Value::VNumber(n) => de::Unexpected::Float(*n as f64),
+			Value::VString(s) => de::Unexpected::Str(s),
+			Value::VList(_) => de::Unexpected::Seq,
+			Value::VMap(_) => de::Unexpected::Map,
+			v => de::Unexpected::Other(v.type_name()),
+		}
+	}
+}
+
+/// A map key. Keys are always strings, but numbers written as keys by [super::to_value] are read back as numbers.
+struct Key(String);
+
+impl<'de> IntoDeserializer<'de, ValueError> for Key {
+	type Deserializer = Self;
+
+	fn into_deserializer(self) -> Self {
+		self
+	}
+}
+
+macro_rules! parse_key {
+	($($method:ident $ty:ty => $visit:ident),*) => {$(
+		fn $method<V: Visitor<'de>>(self, visitor: V) -> ValueResult<V::Value> {
+			match self.0.parse::<$ty>() {
+				Ok(n) => visitor.$visit(n),
+				Err(_) => visitor.visit_string(self.0),
+			}
+		}
+	)*};
+}
+
+impl<'de> de::Deserializer<'de> for Key {
+	type Error = ValueError;
+
+	fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> ValueResult<V::Value> {
+		visitor.visit_string(self.0)
+	}
+
+	fn deserialize_enum<V: Visitor<'de>>(
+		self,
+		name: &'static str,
+		variants: &'static [&'static str],
+		visitor: V,
+	) -> ValueResult<V::Value> {
+		Value::VString(self.0).deserialize_enum(name, variants, visitor)
+	}
+
+	parse_key! {
+		deserialize_i8 i8 => visit_i8, deserialize_i16 i16 => visit_i16, deserialize_i32 i32 => visit_i32,
+		deserialize_i64 i64 => visit_i64, deserialize_u8 u8 => visit_u8, deserialize_u16 u16 => visit_u16,
+		deserialize_u32 u32 => visit_u32, deserialize_u64 u64 => visit_u64, deserialize_f32 f32 => visit_f32,
+		deserialize_f64 f64 => visit_f64
+	}
+
+	forward_to_deserialize_any! {
+		bool i128 u128 char str string bytes byte_buf option unit unit_struct newtype_struct seq tuple
+		tuple_struct map struct identifier ignored_any
+	}
+}
+
+/// An enum variant, and its data if it has any
+struct Enum {
+	variant: String,
+	value: Option<Value>,
+}
+
+impl<'de> de::EnumAccess<'de> for Enum {
+	type Error = ValueError;
+	type Variant = Self;
+
+	fn variant_seed<S: de::DeserializeSeed<'de>>(self, seed: S) -> ValueResult<(S::Value, Self)> {
+		let variant = seed.deserialize(Value::VString(self.variant.clone()))?;
+		Ok((variant, self))
+	}
+}
+
+impl<'de> de::VariantAccess<'de> for Enum {
+	type Error = ValueError;
+
+	fn unit_variant(self) -> ValueResult {
+		match self.value {
+			None | Some(Value::VNil) => Ok(()),
+			Some(v) => Err(de::Error::invalid_type(v.unexpected(), &"a unit variant")),
+		}
+	}
+
+	fn newtype_variant_seed<S: de::DeserializeSeed<'de>>(self, seed: S) -> ValueResult<S::Value> {
+		match self.value {
+			Some(value) => seed.deserialize(value),
+			None => Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"a newtype variant")),
+		}
+	}
+
+	fn tuple_variant<V: Visitor<'de>>(self, _: usize, visitor: V) -> ValueResult<V::Value> {
+		match self.value {
+			Some(value) => de::Deserializer::deserialize_seq(value, visitor),
+			None => Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"a tuple variant")),
+		}
+	}
+
+	fn struct_variant<V: Visitor<'de>>(self, _: &'static [&'static str], visitor: V) -> ValueResult<V::Value> {
+		match self.value {
+			Some(value) => de::Deserializer::deserialize_map(value, visitor),
+			None => Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &"a struct variant")),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeMap;
+
+	use serde::{Deserialize, Serialize};
+
+	use super::*;
+	use crate::{to_value, Engine};
+
+	#[derive(Debug, PartialEq, Serialize, Deserialize)]
+	enum Shape {
+		Point,
+		Circle(f32),
+		Rect { w: u8, h: u8 },
+		Line(i32, i32),
+	}
+
+	#[derive(Debug, PartialEq, Serialize, Deserialize)]
+	struct Config {
+		name: String,
+		retries: u32,
+		ratio: f64,
+		tags: Vec<String>,
+		limits: BTreeMap<String, i16>,
+		parent: Option<Box<Config>>,
+		shapes: Vec<Shape>,
+		pair: (bool, char),
+	}
+
+	fn config() -> Config {
+		Config {
+			name: "svc".into(),
+			retries: 3,
+			ratio: 0.5,
+			tags: vec!["a".into(), "b".into()],
+			limits: BTreeMap::from([("cpu".into(), -2)]),
+			parent: None,
+			shapes: vec![Shape::Point, Shape::Circle(1.5), Shape::Rect { w: 2, h: 3 }, Shape::Line(-1, 1)],
+			pair: (true, 'x'),
+		}
+	}
+
+	#[test]
+	fn round_trip() {
+		let value = to_value(&config()).unwrap();
+		assert_eq!(
+			value.to_string(),
+			r#"{"limits": {"cpu": -2}, "name": "svc", "pair": [true, "x"], "parent": nil, "ratio": 0.5, "retries": 3, "shapes": ["Point", {"Circle": 1.5}, {"Rect": {"h": 3, "w": 2}}, {"Line": [-1, 1]}], "tags": ["a", "b"]}"#
+		);
+		assert_eq!(from_value::<Config>(value), Ok(config()));
+	}
+
+	#[test]
+	fn through_scripts() {
+		let mut engine = Engine::new();
+		engine.register_native("config", |_| Ok(to_value(&config()).unwrap()));
+		let value = engine.eval("let c = config(); c;").unwrap();
+		assert_eq!(from_value::<Config>(value), Ok(config()));
+		let value = engine.eval(r#"config(); ["a", 2];"#).unwrap();
+		assert_eq!(from_value::<(String, u8)>(value), Ok(("a".into(), 2)));
+	}
+
+	#[test]
+	fn mismatches() {
+		assert_eq!(
+			from_value::<u8>(Value::VNumber(300.)),
+			Err(ValueError::Custom("invalid value: integer `300`, expected u8".into()))
+		);
+		assert_eq!(
+			from_value::<Vec<String>>(Value::VString("x".into())),
+			Err(ValueError::Custom("invalid type: string \"x\", expected a sequence".into()))
+		);
+		assert!(matches!(from_value::<Config>(Value::VMap(BTreeMap::new())), Err(ValueError::Custom(msg)) if msg.contains("missing field")));
+		assert_eq!(from_value::<Shape>(Value::VString("Hexagon".into())).map_err(|e| e.to_string().contains("unknown variant")), Err(true));
+		assert_eq!(from_value::<String>(Value::VNative("f".into())), Err(ValueError::Unsupported("fn")));
+		assert_eq!(to_value(&u64::MAX), Err(ValueError::Precision(u64::MAX.to_string())));
+		assert_eq!(to_value(&BTreeMap::from([(vec![1], 1)])), Err(ValueError::KeyMustBeString));
+		assert_eq!(to_value(&0.1f64), Ok(Value::VNumber(0.1)));
+		assert_eq!(to_value(&f64::INFINITY), Ok(Value::VNumber(f32::INFINITY)));
+		assert_eq!(to_value(&1e300f64), Err(ValueError::Precision(1e300f64.to_string())));
+	}
+
+	#[test]
+	fn map_keys() {
+		let numbers = BTreeMap::from([(1, "a".to_string()), (-20, "b".into())]);
+		assert_eq!(from_value::<BTreeMap<i32, String>>(to_value(&numbers).unwrap()), Ok(numbers));
+		#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+		enum Color { Red, Blue }
+		let colors = BTreeMap::from([(Color::Red, 1), (Color::Blue, 2)]);
+		assert_eq!(from_value::<BTreeMap<Color, u8>>(to_value(&colors).unwrap()), Ok(colors));
+		assert!(from_value::<BTreeMap<u8, u8>>(to_value(&BTreeMap::from([("x", 1)])).unwrap()).is_err());
+	}
+}
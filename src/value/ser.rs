@@ -0,0 +1,319 @@
+use std::collections::BTreeMap;
+
+use serde::{ser, Serialize};
+
+use crate::{Value, error::value::*};
+
+/// Convert any serializable Rust data into a blush value.
+/// Structs and maps become maps, sequences and tuples become lists, and `None` and `()` become nil.
+/// Enum variants without data become strings, and variants with data become maps from the variant's
+/// name to its data.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> ValueResult<Value> {
+	value.serialize(Serializer)
+}
+
+pub struct Serializer;
+
+/// Convert an integer to a number, as long as it is small enough to be stored exactly
+fn integer<N: TryInto<i32> + ToString + Copy>(n: N) -> ValueResult<Value> {
+	const EXACT: i32 = 1 << f32::MANTISSA_DIGITS;
+	match n.try_into() {
+		Ok(i) if (-EXACT..=EXACT).contains(&i) => Ok(Value::VNumber(i as f32)),
+		_ => Err(ValueError::Precision(n.to_string())),
+	}
+}
+
+impl ser::Serializer for Serializer {
+	type Ok = Value;
+	type Error = ValueError;
+	type SerializeSeq = SerializeList;
+	type SerializeTuple = SerializeList;
+	type SerializeTupleStruct = SerializeList;
+	type SerializeTupleVariant = SerializeList;
+	type SerializeMap = SerializeMap;
+	type SerializeStruct = SerializeMap;
+	type SerializeStructVariant = SerializeMap;
+
+	fn serialize_bool(self, v: bool) -> ValueResult<Value> {
+		Ok(Value::VBool(v))
+	}
+
+	fn serialize_i8(self, v: i8) -> ValueResult<Value> {
+		integer(v)
+	}
+
+	fn serialize_i16(self, v: i16) -> ValueResult<Value> {
+		integer(v)
+	}
+
+	fn serialize_i32(self, v: i32) -> ValueResult<Value> {
+		integer(v)
+	}
+
+	fn serialize_i64(self, v: i64) -> ValueResult<Value> {
+		integer(v)
+	}
+
+	fn serialize_u8(self, v: u8) -> ValueResult<Value> {
+		integer(v)
+	}
+
+	fn serialize_u16(self, v: u16) -> ValueResult<Value> {
+		integer(v)
+	}
+
+	fn serialize_u32(self, v: u32) -> ValueResult<Value> {
+		integer(v)
+	}
+
+	fn serialize_u64(self, v: u64) -> ValueResult<Value> {
+		integer(v)
+	}
+
+	fn serialize_f32(self, v: f32) -> ValueResult<Value> {
+		Ok(Value::VNumber(v))
+	}
+
+	fn serialize_f64(self, v: f64) -> ValueResult<Value> {
+		// Rounded to the nearest f32 like `json.parse` does, as long as it doesn't become infinite
+		match v.is_finite() && !(v as f32).is_finite() {
+			true => Err(ValueError::Precision(v.to_string())),
+			false => Ok(Value::VNumber(v as f32)),
+		}
+	}
+
+	fn serialize_char(self, v: char) -> ValueResult<Value> {
+		Ok(Value::VString(v.to_string()))
+	}
+
+	fn serialize_str(self, v: &str) -> ValueResult<Value> {
+		Ok(Value::VString(v.into()))
+	}
+
+	fn serialize_bytes(self, v: &[u8]) -> ValueResult<Value> {
+		Ok(Value::VList(v.iter().map(|b| Value::VNumber(*b as f32)).collect()))
+	}
+
+	fn serialize_none(self) -> ValueResult<Value> {
+		Ok(Value::VNil)
+	}
+
+	fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> ValueResult<Value> {
+		value.serialize(self)
+	}
+
+	fn serialize_unit(self) -> ValueResult<Value> {
+		Ok(Value::VNil)
+	}
+
+	fn serialize_unit_struct(self, _: &'static str) -> ValueResult<Value> {
+		Ok(Value::VNil)
+	}
+
+	fn serialize_unit_variant(self, _: &'static str, _: u32, variant: &'static str) -> ValueResult<Value> {
+		Ok(Value::VString(variant.into()))
+	}
+
+	fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _: &'static str, value: &T) -> ValueResult<Value> {
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_variant<T: Serialize + ?Sized>(
+		self,
+		_: &'static str,
+		_: u32,
+		variant: &'static str,
+		value: &T,
+	) -> ValueResult<Value> {
+		Ok(Value::VMap(BTreeMap::from([(variant.to_string(), to_value(value)?)])))
+	}
+
+	fn serialize_seq(self, len: Option<usize>) -> ValueResult<SerializeList> {
+		Ok(SerializeList {
+			variant: None,
+			items: Vec::with_capacity(len.unwrap_or(0)),
+		})
+	}
+
+	fn serialize_tuple(self, len: usize) -> ValueResult<SerializeList> {
+		self.serialize_seq(Some(len))
+	}
+
+	fn serialize_tuple_struct(self, _: &'static str, len: usize) -> ValueResult<SerializeList> {
+		self.serialize_seq(Some(len))
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		_: &'static str,
+		_: u32,
+		variant: &'static str,
+		len: usize,
+	) -> ValueResult<SerializeList> {
+		Ok(SerializeList {
+			variant: Some(variant),
+			items: Vec::with_capacity(len),
+		})
+	}
+
+	fn serialize_map(self, _: Option<usize>) -> ValueResult<SerializeMap> {
+		Ok(SerializeMap {
+			variant: None,
+			map: BTreeMap::new(),
+			key: None,
+		})
+	}
+
+	fn serialize_struct(self, _: &'static str, len: usize) -> ValueResult<SerializeMap> {
+		self.serialize_map(Some(len))
+	}
+
+	fn serialize_struct_variant(
+		self,
+		_: &'static str,
+		_: u32,
+		variant: &'static str,
+		_: usize,
+	) -> ValueResult<SerializeMap> {
+		Ok(SerializeMap {
+			variant: Some(variant),
+			map: BTreeMap::new(),
+			key: None,
+		})
+	}
+}
+
+/// Wrap the data of an enum variant in a map from the variant's name
+fn variant(variant: Option<&'static str>, value: Value) -> Value {
+	match variant {
+		Some(variant) => Value::VMap(BTreeMap::from([(variant.to_string(), value)])),
+		None => value,
+	}
+}
+
+pub struct SerializeList {
+	variant: Option<&'static str>,
+	items: Vec<Value>,
+}
+
+impl SerializeList {
+	fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> ValueResult {
+		self.items.push(to_value(value)?);
+		Ok(())
+	}
+
+	fn finish(self) -> ValueResult<Value> {
+		Ok(variant(self.variant, Value::VList(self.items)))
+	}
+}
+
+impl ser::SerializeSeq for SerializeList {
+	type Ok = Value;
+	type Error = ValueError;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> ValueResult {
+		self.push(value)
+	}
+
+	fn end(self) -> ValueResult<Value> {
+		self.finish()
+	}
+}
+
+impl ser::SerializeTuple for SerializeList {
+	type Ok = Value;
+	type Error = ValueError;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> ValueResult {
+		self.push(value)
+	}
+
+	fn end(self) -> ValueResult<Value> {
+		self.finish()
+	}
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+	type Ok = Value;
+	type Error = ValueError;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> ValueResult {
+		self.push(value)
+	}
+
+	fn end(self) -> ValueResult<Value> {
+		self.finish()
+	}
+}
+
+impl ser::SerializeTupleVariant for SerializeList {
+	type Ok = Value;
+	type Error = ValueError;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> ValueResult {
+		self.push(value)
+	}
+
+	fn end(self) -> ValueResult<Value> {
+		self.finish()
+	}
+}
+
+pub struct SerializeMap {
+	variant: Option<&'static str>,
+	map: BTreeMap<String, Value>,
+	/// The key of the entry being serialized
+	key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+	type Ok = Value;
+	type Error = ValueError;
+
+	fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> ValueResult {
+		self.key = Some(match to_value(key)? {
+			Value::VString(key) => key,
+			Value::VNumber(n) => n.to_string(),
+			_ => return Err(ValueError::KeyMustBeString),
+		});
+		Ok(())
+	}
+
+	fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> ValueResult {
+		let key = self.key.take().ok_or(ValueError::Custom("map value without a key".into()))?;
+		self.map.insert(key, to_value(value)?);
+		Ok(())
+	}
+
+	fn end(self) -> ValueResult<Value> {
+		Ok(variant(self.variant, Value::VMap(self.map)))
+	}
+}
+
+impl ser::SerializeStruct for SerializeMap {
+	type Ok = Value;
+	type Error = ValueError;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> ValueResult {
+		self.map.insert(key.into(), to_value(value)?);
+		Ok(())
+	}
+
+	fn end(self) -> ValueResult<Value> {
+		ser::SerializeMap::end(self)
+	}
+}
+
+impl ser::SerializeStructVariant for SerializeMap {
+	type Ok = Value;
+	type Error = ValueError;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> ValueResult {
+		self.map.insert(key.into(), to_value(value)?);
+		Ok(())
+	}
+
+	fn end(self) -> ValueResult<Value> {
+		ser::SerializeMap::end(self)
+	}
+}